@@ -0,0 +1,22 @@
+//! Prints the magic numbers used for sliding attacks, in the form they are
+//! checked in to `src/movegen/sliders/magic.rs`.
+
+use chess::movegen::sliders::magic::{
+    find_bishop_magics, find_rook_magics, MAGIC_SEED,
+};
+
+fn print_table(name: &str, magics: &[u64; 64]) {
+    println!("#[rustfmt::skip]");
+    println!("pub const {name}: [u64; 64] = [");
+    for row in magics.chunks(4) {
+        let row: Vec<_> = row.iter().map(|m| format!("{m:#018x},")).collect();
+        println!("    {}", row.join(" "));
+    }
+    println!("];");
+}
+
+fn main() {
+    print_table("BISHOP_MAGICS", &find_bishop_magics(MAGIC_SEED));
+    println!();
+    print_table("ROOK_MAGICS", &find_rook_magics(MAGIC_SEED));
+}
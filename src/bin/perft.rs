@@ -0,0 +1,138 @@
+//! Counts the leaf nodes of the legal move tree from a position, for checking
+//! and benchmarking move generation.
+//!
+//! ```text
+//! perft [--fen <FEN>] --depth <N> [--divide] [--hash-mb <M>] [--threads <T>]
+//! ```
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chess::board::fen::STARTING_FEN;
+use chess::board::position::Position;
+use chess::movegen::generate_legal_moves;
+use chess::movegen::perft::{perft_bulk, perft_hashed, PerftTable};
+use chess::moves::Move;
+
+const USAGE: &str = "usage: perft [--fen <FEN>] --depth <N> [--divide] \
+                     [--hash-mb <M>] [--threads <T>]";
+
+struct Options {
+    fen:     String,
+    depth:   u32,
+    divide:  bool,
+    hash_mb: usize,
+    threads: usize,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        fen:     STARTING_FEN.to_string(),
+        depth:   0,
+        divide:  false,
+        hash_mb: 0,
+        threads: 1,
+    };
+    let mut depth = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--fen" => options.fen = value()?,
+            "--depth" => depth = Some(parse(&arg, &value()?)?),
+            "--divide" => options.divide = true,
+            "--hash-mb" => options.hash_mb = parse(&arg, &value()?)?,
+            "--threads" => options.threads = parse(&arg, &value()?)?,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unexpected argument {arg:?}")),
+        }
+    }
+
+    options.depth = depth.ok_or("--depth is required")?;
+    if options.threads == 0 {
+        return Err("--threads must be at least 1".to_string());
+    }
+    Ok(options)
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value {value:?} for {arg}"))
+}
+
+/// Counts the nodes beneath each root move, sharing the moves out between the
+/// given number of threads.
+fn count_root_moves(
+    position: &Position,
+    depth: u32,
+    threads: usize,
+    table: Option<&PerftTable>,
+) -> Vec<(Move, u64)> {
+    let moves = generate_legal_moves(position);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![0; moves.len()]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.min(moves.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&m) = moves.get(i) else { break };
+
+                let mut child = position.with_move(m);
+                let nodes = match table {
+                    Some(table) => perft_hashed(&mut child, depth - 1, table),
+                    None => perft_bulk(&mut child, depth - 1),
+                };
+                results.lock().unwrap()[i] = nodes;
+            });
+        }
+    });
+
+    moves.into_iter().zip(results.into_inner().unwrap()).collect()
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        },
+    };
+
+    let mut position = match Position::from_fen(&options.fen) {
+        Ok(position) => position,
+        Err(error) => {
+            eprintln!("invalid FEN: {error}");
+            return ExitCode::from(2);
+        },
+    };
+    let table = (options.hash_mb > 0).then(|| PerftTable::new(options.hash_mb));
+
+    let start = Instant::now();
+    let nodes = if options.depth == 0 {
+        perft_bulk(&mut position, 0)
+    } else {
+        let divided = count_root_moves(
+            &position,
+            options.depth,
+            options.threads,
+            table.as_ref(),
+        );
+        if options.divide {
+            for (m, nodes) in &divided {
+                println!("{m}: {nodes}");
+            }
+            println!();
+        }
+        divided.iter().map(|&(_, nodes)| nodes).sum()
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!("nodes: {nodes}");
+    println!("time:  {elapsed:.3}s");
+    println!("nps:   {:.0}", nodes as f64 / elapsed.max(f64::EPSILON));
+    ExitCode::SUCCESS
+}
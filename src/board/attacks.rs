@@ -0,0 +1,121 @@
+//! Attack queries on a Position: which pieces attack a square, and which
+//! squares a side attacks.
+
+use super::bitboard::*;
+use super::position::Position;
+use crate::movegen::king::{all_king_attacks, king_attacks};
+use crate::movegen::knights::{all_knight_attacks, knight_attacks};
+use crate::movegen::pawns::{black_pawn_attacks, white_pawn_attacks};
+use crate::movegen::sliders::{bishop_attacks, rook_attacks};
+use crate::piece::PieceColor;
+use crate::piece::PieceType::*;
+use crate::types::*;
+
+impl Position {
+    /// Returns the pieces of both colors that attack the given square, with
+    /// sliding attacks blocked by the given occupancy rather than the
+    /// position's own. Passing a different occupancy lets callers see through
+    /// pieces that are about to move, or have been exchanged off.
+    pub fn attackers_to(
+        &self,
+        square: Square,
+        occupancy: BitBoard,
+    ) -> BitBoard {
+        let target = square.bitboard();
+        let queens = self[WhiteQueen] | self[BlackQueen];
+
+        (black_pawn_attacks(target) & self[WhitePawn])
+            | (white_pawn_attacks(target) & self[BlackPawn])
+            | (knight_attacks(square) & (self[WhiteKnight] | self[BlackKnight]))
+            | (king_attacks(square) & (self[WhiteKing] | self[BlackKing]))
+            | (bishop_attacks(square, occupancy)
+                & (self[WhiteBishop] | self[BlackBishop] | queens))
+            | (rook_attacks(square, occupancy)
+                & (self[WhiteRook] | self[BlackRook] | queens))
+    }
+
+    /// Returns true if any piece of the given color attacks the given square.
+    pub fn is_square_attacked(&self, square: Square, by: PieceColor) -> bool {
+        self.attackers_to(square, self.all_pieces()) & self[by] != 0
+    }
+
+    /// Returns every square attacked by the pieces of the given color,
+    /// whether or not it's occupied.
+    pub fn attacks_by(&self, color: PieceColor) -> BitBoard {
+        let occupancy = self.all_pieces();
+        let pawns = self[WhitePawn.with_color(color)];
+        let queens = self[WhiteQueen.with_color(color)];
+
+        let mut attacks = match color {
+            PieceColor::White => white_pawn_attacks(pawns),
+            PieceColor::Black => black_pawn_attacks(pawns),
+        };
+        attacks |= all_knight_attacks(self[WhiteKnight.with_color(color)]);
+        attacks |= all_king_attacks(self[WhiteKing.with_color(color)]);
+
+        for square in (self[WhiteBishop.with_color(color)] | queens).squares() {
+            attacks |= bishop_attacks(square, occupancy);
+        }
+        for square in (self[WhiteRook.with_color(color)] | queens).squares() {
+            attacks |= rook_attacks(square, occupancy);
+        }
+
+        attacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(fen: &str) -> Position { Position::from_fen(fen).unwrap() }
+
+    #[test]
+    fn attackers_include_both_colors() {
+        use Square::*;
+
+        let position = position("4k3/8/2n5/3p4/4P3/8/8/R3K2R w - - 0 1");
+        assert_eq!(
+            position.attackers_to(D5, position.all_pieces()),
+            E4.bitboard()
+        );
+        assert_eq!(
+            position.attackers_to(E4, position.all_pieces()),
+            D5.bitboard()
+        );
+        assert_eq!(
+            position.attackers_to(D1, position.all_pieces()),
+            A1.bitboard() | E1.bitboard()
+        );
+        // Removing the king lets the h1 rook through.
+        let occupancy = position.all_pieces() & !E1.bitboard();
+        assert_eq!(
+            position.attackers_to(D1, occupancy),
+            A1.bitboard() | E1.bitboard() | H1.bitboard()
+        );
+    }
+
+    #[test]
+    fn squares_can_be_checked_for_attacks() {
+        use Square::*;
+
+        let position = position("4k3/8/2n5/3p4/4P3/8/8/R3K2R w - - 0 1");
+        assert!(position.is_square_attacked(E4, PieceColor::Black));
+        assert!(position.is_square_attacked(B4, PieceColor::Black));
+        assert!(!position.is_square_attacked(E6, PieceColor::Black));
+        assert!(position.is_square_attacked(A8, PieceColor::White));
+        assert!(!position.is_square_attacked(E8, PieceColor::White));
+    }
+
+    #[test]
+    fn attack_maps_cover_every_piece() {
+        let start = Position::starting_position();
+        assert_eq!(start.attacks_by(PieceColor::White), 0xffff7e);
+        assert_eq!(start.attacks_by(PieceColor::Black), 0x7effff0000000000);
+
+        let position = position("8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+        let attacks = position.attacks_by(PieceColor::White);
+        assert_eq!(attacks & RANK_1, 0x3e);
+        assert_eq!(attacks & FILE_A, FILE_A & !RANK_1);
+    }
+}
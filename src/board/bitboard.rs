@@ -1,3 +1,5 @@
+use super::square::Square;
+
 pub trait IsBitBoard: Copy {
     fn inc_rank_by(self, amount: i8) -> Self;
     fn inc_rank(self) -> Self;
@@ -5,6 +7,9 @@ pub trait IsBitBoard: Copy {
 
     fn inc_file(self) -> Self;
     fn dec_file(self) -> Self;
+
+    /// Iterates over the squares in this BitBoard, from A1 to H8.
+    fn squares(self) -> Squares;
 }
 
 pub type BitBoard = u64;
@@ -13,10 +18,36 @@ impl IsBitBoard for BitBoard {
     fn inc_rank(self) -> Self { self << 8 }
     fn dec_rank(self) -> Self { self >> 8 }
 
-    fn inc_file(self) -> Self { self << 1 & FILE_A }
-    fn dec_file(self) -> Self { self >> 1 & FILE_H }
+    fn inc_file(self) -> Self { self << 1 & !FILE_A }
+    fn dec_file(self) -> Self { self >> 1 & !FILE_H }
+
+    fn squares(self) -> Squares { Squares(self) }
 }
 
+/// Iterator over the squares in a BitBoard. See `IsBitBoard::squares`.
+#[derive(Clone, Copy, Debug)]
+pub struct Squares(BitBoard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = Square::from_index(self.0.trailing_zeros() as u8);
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
 pub const FULL: BitBoard = 0xff_ff_ff_ff_ff_ff_ff_ff;
 pub const EMPTY: BitBoard = 0x00_00_00_00_00_00_00_00;
 
@@ -64,4 +95,20 @@ mod tests {
         assert_eq!(KINGSIDE | QUEENSIDE, FULL);
         assert_eq!(KINGSIDE & QUEENSIDE, EMPTY);
     }
+
+    #[test]
+    fn file_shifts_do_not_wrap() {
+        assert_eq!(FILE_H.inc_file(), EMPTY);
+        assert_eq!(FILE_A.dec_file(), EMPTY);
+        assert_eq!(FILE_A.inc_file(), FILE_B);
+        assert_eq!(FILE_H.dec_file(), FILE_G);
+    }
+
+    #[test]
+    fn squares_are_iterated_in_order() {
+        use Square::*;
+        let board = A1.bitboard() | E4.bitboard() | H8.bitboard();
+        assert_eq!(board.squares().collect::<Vec<_>>(), [A1, E4, H8]);
+        assert_eq!(EMPTY.squares().len(), 0);
+    }
 }
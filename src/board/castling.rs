@@ -0,0 +1,129 @@
+use std::ops::{BitAnd, BitOr, Not, Sub};
+
+use crate::piece::PieceColor;
+use crate::types::*;
+
+/// The squares that the rooks start on in standard chess, indexed in the same
+/// order as the bits of a CastlingRights (see `CastlingRights::index`).
+pub const STANDARD_CASTLING_ROOKS: [Square; 4] =
+    [Square::H1, Square::A1, Square::H8, Square::A8];
+
+/// The set of castling moves that are still available to each player, stored
+/// as a 4-bit set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CastlingRights(u8);
+
+impl CastlingRights {
+    pub const NONE: Self = Self(0);
+    pub const WHITE_KINGSIDE: Self = Self(0b0001);
+    pub const WHITE_QUEENSIDE: Self = Self(0b0010);
+    pub const BLACK_KINGSIDE: Self = Self(0b0100);
+    pub const BLACK_QUEENSIDE: Self = Self(0b1000);
+    pub const WHITE: Self = Self(0b0011);
+    pub const BLACK: Self = Self(0b1100);
+    pub const ALL: Self = Self(0b1111);
+
+    /// Constructs a set from its raw bits. Bits above the lowest four are
+    /// ignored.
+    pub const fn from_bits(bits: u8) -> Self { Self(bits & Self::ALL.0) }
+    pub const fn bits(self) -> u8 { self.0 }
+
+    pub const fn is_empty(self) -> bool { self.0 == 0 }
+
+    /// The index (0 to 3) of the lowest right in this set, for use with
+    /// per-right tables such as `STANDARD_CASTLING_ROOKS`.
+    pub const fn index(self) -> usize { self.0.trailing_zeros() as usize }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self { Self(self.0 | other.0) }
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub fn insert(&mut self, other: Self) { *self = self.union(other); }
+    pub fn remove(&mut self, other: Self) { *self = self.difference(other); }
+
+    /// Returns both of the given color's castling rights.
+    pub const fn for_color(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => Self::WHITE,
+            PieceColor::Black => Self::BLACK,
+        }
+    }
+
+    /// Returns the given color's kingside castling right.
+    pub const fn kingside(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => Self::WHITE_KINGSIDE,
+            PieceColor::Black => Self::BLACK_KINGSIDE,
+        }
+    }
+
+    /// Returns the given color's queenside castling right.
+    pub const fn queenside(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => Self::WHITE_QUEENSIDE,
+            PieceColor::Black => Self::BLACK_QUEENSIDE,
+        }
+    }
+
+    /// Iterates over each individual right in this set.
+    pub fn iter(self) -> impl Iterator<Item = Self> {
+        (0..4).map(|i| Self(1 << i)).filter(move |&right| self.contains(right))
+    }
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self { Self::NONE }
+}
+
+impl BitOr for CastlingRights {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output { self.union(rhs) }
+}
+
+impl BitAnd for CastlingRights {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output { self.intersection(rhs) }
+}
+
+impl Sub for CastlingRights {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output { self.difference(rhs) }
+}
+
+impl Not for CastlingRights {
+    type Output = Self;
+    fn not(self) -> Self::Output { Self::ALL.difference(self) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_operations_are_correct() {
+        let mut rights = CastlingRights::ALL;
+        rights.remove(CastlingRights::WHITE_QUEENSIDE);
+        assert!(!rights.contains(CastlingRights::WHITE));
+        assert!(rights.contains(CastlingRights::BLACK));
+        assert_eq!(
+            rights & CastlingRights::WHITE,
+            CastlingRights::WHITE_KINGSIDE
+        );
+        assert_eq!(!rights, CastlingRights::WHITE_QUEENSIDE);
+        assert_eq!(
+            rights - CastlingRights::BLACK,
+            CastlingRights::kingside(PieceColor::White)
+        );
+        assert_eq!(CastlingRights::from_bits(0xff), CastlingRights::ALL);
+        assert_eq!(rights.iter().count(), 3);
+        assert!((rights - rights).is_empty());
+    }
+}
@@ -0,0 +1,510 @@
+//! Parsing and serialization of Forsyth-Edwards Notation (FEN).
+
+use std::fmt;
+
+use super::castling::CastlingRights;
+use super::position::{Position, PositionBuilder};
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// The FEN of the standard starting position.
+pub const STARTING_FEN: &str =
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// One of the six whitespace-separated fields of a FEN string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+/// What went wrong while parsing a FEN field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenErrorKind {
+    /// The field was absent.
+    Missing,
+    /// A character that isn't valid in this field (or at this point of it).
+    UnexpectedChar(char),
+    /// A rank described more or fewer than eight squares.
+    BadRankLength,
+    /// The piece placement did not describe exactly eight ranks.
+    BadRankCount,
+    /// The field is not a valid square name.
+    BadSquare,
+    /// The field is not a valid number.
+    BadNumber,
+    /// A side doesn't have exactly one king.
+    BadKingCount,
+    /// The en passant square isn't on the rank that the side to move could
+    /// capture onto.
+    BadEnPassantRank,
+    /// There was more input after the last field.
+    TrailingInput,
+}
+
+/// The error returned when a FEN string cannot be parsed. Records which field
+/// was invalid and the (zero-based) character index at which the problem was
+/// found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FenError {
+    pub field: FenField,
+    pub index: usize,
+    pub kind:  FenErrorKind,
+}
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FenField::Placement => "piece placement",
+            FenField::SideToMove => "side to move",
+            FenField::Castling => "castling",
+            FenField::EnPassant => "en passant",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveNumber => "fullmove number",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for FenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenErrorKind::Missing => write!(f, "field is missing"),
+            FenErrorKind::UnexpectedChar(c) => {
+                write!(f, "unexpected character {c:?}")
+            },
+            FenErrorKind::BadRankLength => {
+                write!(f, "rank does not have eight squares")
+            },
+            FenErrorKind::BadRankCount => {
+                write!(f, "board does not have eight ranks")
+            },
+            FenErrorKind::BadSquare => write!(f, "invalid square"),
+            FenErrorKind::BadNumber => write!(f, "invalid number"),
+            FenErrorKind::BadKingCount => {
+                write!(f, "each side must have exactly one king")
+            },
+            FenErrorKind::BadEnPassantRank => {
+                write!(f, "en passant square is on the wrong rank")
+            },
+            FenErrorKind::TrailingInput => write!(f, "unexpected extra input"),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid FEN {} field at character {}: {}",
+            self.field, self.index, self.kind
+        )
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Splits a FEN string into its fields, keeping the character index at which
+/// each field starts.
+fn split_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+
+    for (i, (byte, c)) in fen.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((i, byte)),
+            (true, Some((index, from))) => {
+                fields.push((index, &fen[from..byte]));
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    if let Some((index, from)) = start {
+        fields.push((index, &fen[from..]));
+    }
+
+    fields
+}
+
+impl Position {
+    /// Parses a position from a FEN string. The halfmove clock and fullmove
+    /// number may be omitted, in which case they default to 0 and 1.
+    ///
+    /// Each side must have exactly one king, and the en passant square, if
+    /// any, must be one that the side to move could capture onto.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields = split_fields(fen);
+        let end = fen.chars().count();
+        let field = |i: usize, name: FenField| {
+            fields.get(i).copied().ok_or(FenError {
+                field: name,
+                index: end,
+                kind:  FenErrorKind::Missing,
+            })
+        };
+
+        let placement = field(0, FenField::Placement)?;
+        let mut position = parse_placement(placement)?;
+        let side = parse_side_to_move(field(1, FenField::SideToMove)?)?;
+        position.set_side_to_move(side);
+        position.set_castling_rights(parse_castling(field(
+            2,
+            FenField::Castling,
+        )?)?);
+        let en_passant = field(3, FenField::EnPassant)?;
+        if let Some(target) = parse_en_passant(en_passant, side)? {
+            position.set_en_passant_targets(target.bitboard());
+        }
+        if let Some(&clock) = fields.get(4) {
+            position.set_halfmove_clock(parse_number(
+                clock,
+                FenField::HalfmoveClock,
+            )?);
+        }
+        if let Some(&number) = fields.get(5) {
+            position.set_fullmove_number(parse_number(
+                number,
+                FenField::FullmoveNumber,
+            )?);
+        }
+        if let Some(&(index, _)) = fields.get(6) {
+            return Err(FenError {
+                field: FenField::FullmoveNumber,
+                index,
+                kind: FenErrorKind::TrailingInput,
+            });
+        }
+
+        // Without exactly one king a side, there's no telling what is legal.
+        let kings = [PieceType::WhiteKing, PieceType::BlackKing];
+        if kings.iter().any(|&king| position[king].count_ones() != 1) {
+            return Err(FenError {
+                field: FenField::Placement,
+                index: placement.0,
+                kind:  FenErrorKind::BadKingCount,
+            });
+        }
+
+        Ok(position)
+    }
+
+    /// Serializes this position as a FEN string with all six fields.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self[Square::from_file_rank(file, rank)] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push((b'0' + empty) as char);
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
+                    },
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push((b'0' + empty) as char);
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.side_to_move() {
+            PieceColor::White => 'w',
+            PieceColor::Black => 'b',
+        });
+
+        fen.push(' ');
+        let rights = self.castling_rights();
+        if rights.is_empty() {
+            fen.push('-');
+        }
+        for (right, c) in CASTLING_CHARS {
+            if rights.contains(right) {
+                fen.push(c);
+            }
+        }
+
+        fen.push(' ');
+        match self.en_passant_targets() {
+            0 => fen.push('-'),
+            targets => {
+                let square = Square::from_index(targets.trailing_zeros() as u8);
+                fen.push_str(&square.to_string());
+            },
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock(),
+            self.fullmove_number()
+        ));
+
+        fen
+    }
+}
+
+const CASTLING_CHARS: [(CastlingRights, char); 4] = [
+    (CastlingRights::WHITE_KINGSIDE, 'K'),
+    (CastlingRights::WHITE_QUEENSIDE, 'Q'),
+    (CastlingRights::BLACK_KINGSIDE, 'k'),
+    (CastlingRights::BLACK_QUEENSIDE, 'q'),
+];
+
+fn parse_placement((start, text): (usize, &str)) -> Result<Position, FenError> {
+    let mut position = PositionBuilder::new().build();
+    let error = |offset: usize, kind| FenError {
+        field: FenField::Placement,
+        index: start + offset,
+        kind,
+    };
+
+    let mut rank = 7u8;
+    let mut file = 0u8;
+
+    for (offset, c) in text.chars().enumerate() {
+        match c {
+            '/' => {
+                if file != 8 {
+                    return Err(error(offset, FenErrorKind::BadRankLength));
+                }
+                if rank == 0 {
+                    return Err(error(offset, FenErrorKind::BadRankCount));
+                }
+                rank -= 1;
+                file = 0;
+            },
+            '1'..='8' => {
+                file += c as u8 - b'0';
+                if file > 8 {
+                    return Err(error(offset, FenErrorKind::BadRankLength));
+                }
+            },
+            _ => {
+                let piece = PieceType::from_char(c)
+                    .ok_or(error(offset, FenErrorKind::UnexpectedChar(c)))?;
+                if file >= 8 {
+                    return Err(error(offset, FenErrorKind::BadRankLength));
+                }
+                position.put_piece(piece, Square::from_file_rank(file, rank));
+                file += 1;
+            },
+        }
+    }
+
+    let end = text.chars().count();
+    if file != 8 {
+        return Err(error(end, FenErrorKind::BadRankLength));
+    }
+    if rank != 0 {
+        return Err(error(end, FenErrorKind::BadRankCount));
+    }
+
+    Ok(position)
+}
+
+fn parse_side_to_move(
+    (start, text): (usize, &str),
+) -> Result<PieceColor, FenError> {
+    match text {
+        "w" => Ok(PieceColor::White),
+        "b" => Ok(PieceColor::Black),
+        _ => {
+            // Point at the first character that makes this field invalid.
+            let (offset, c) = text
+                .chars()
+                .enumerate()
+                .find(|&(i, c)| i > 0 || !matches!(c, 'w' | 'b'))
+                .unwrap_or((0, ' '));
+            Err(FenError {
+                field: FenField::SideToMove,
+                index: start + offset,
+                kind:  FenErrorKind::UnexpectedChar(c),
+            })
+        },
+    }
+}
+
+fn parse_castling(
+    (start, text): (usize, &str),
+) -> Result<CastlingRights, FenError> {
+    if text == "-" {
+        return Ok(CastlingRights::NONE);
+    }
+
+    let mut rights = CastlingRights::NONE;
+    for (offset, c) in text.chars().enumerate() {
+        match CASTLING_CHARS.iter().find(|&&(_, ch)| ch == c) {
+            Some(&(right, _)) if !rights.contains(right) => {
+                rights.insert(right);
+            },
+            _ => {
+                return Err(FenError {
+                    field: FenField::Castling,
+                    index: start + offset,
+                    kind:  FenErrorKind::UnexpectedChar(c),
+                })
+            },
+        }
+    }
+
+    Ok(rights)
+}
+
+fn parse_en_passant(
+    (start, text): (usize, &str),
+    side: PieceColor,
+) -> Result<Option<Square>, FenError> {
+    if text == "-" {
+        return Ok(None);
+    }
+
+    let error =
+        |kind| FenError { field: FenField::EnPassant, index: start, kind };
+    let square: Square =
+        text.parse().map_err(|_| error(FenErrorKind::BadSquare))?;
+    // The square that the opponent's pawn skipped over.
+    let rank = match side {
+        PieceColor::White => 5,
+        PieceColor::Black => 2,
+    };
+    match square.rank() {
+        r if r == rank => Ok(Some(square)),
+        2 | 5 => Err(error(FenErrorKind::BadEnPassantRank)),
+        _ => Err(error(FenErrorKind::BadSquare)),
+    }
+}
+
+fn parse_number(
+    (start, text): (usize, &str),
+    field: FenField,
+) -> Result<u32, FenError> {
+    if let Some((offset, c)) =
+        text.chars().enumerate().find(|(_, c)| !c.is_ascii_digit())
+    {
+        return Err(FenError {
+            field,
+            index: start + offset,
+            kind: FenErrorKind::UnexpectedChar(c),
+        });
+    }
+
+    text.parse().map_err(|_| FenError {
+        field,
+        index: start,
+        kind: FenErrorKind::BadNumber,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_fen_round_trips() {
+        let position = Position::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(position, Position::starting_position());
+        assert_eq!(position.to_fen(), STARTING_FEN);
+    }
+
+    #[test]
+    fn all_fields_round_trip() {
+        let fens = [
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - \
+             0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b Kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/8/8/8/4K3 b - - 99 150",
+        ];
+
+        for fen in fens {
+            let position = Position::from_fen(fen).unwrap();
+            assert_eq!(position.to_fen(), fen);
+            assert!(position.is_consistent());
+        }
+    }
+
+    #[test]
+    fn fields_are_parsed() {
+        let position = Position::from_fen(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR b Kq e3 4 12",
+        )
+        .unwrap();
+
+        assert_eq!(position.side_to_move(), PieceColor::Black);
+        assert_eq!(
+            position.castling_rights(),
+            CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE
+        );
+        assert_eq!(position.en_passant_targets(), Square::E3.bitboard());
+        assert_eq!(position.halfmove_clock(), 4);
+        assert_eq!(position.fullmove_number(), 12);
+        assert_eq!(position[Square::C5], Some(PieceType::BlackPawn));
+    }
+
+    #[test]
+    fn move_counters_are_optional() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(position.halfmove_clock(), 0);
+        assert_eq!(position.fullmove_number(), 1);
+    }
+
+    #[test]
+    fn errors_report_field_and_index() {
+        use FenErrorKind::*;
+        use FenField::*;
+
+        let cases = [
+            ("", Placement, 0, Missing),
+            ("8/8/8/8/8/8/8/8", SideToMove, 15, Missing),
+            ("8/8/8/8/8/8/8/7", Placement, 15, BadRankLength),
+            ("8/8/8/8/8/8/8/9 w - -", Placement, 14, UnexpectedChar('9')),
+            ("8/8/8/8/8/8/8/45 w - -", Placement, 15, BadRankLength),
+            ("8/8/8/8/8/8/8 w - -", Placement, 13, BadRankCount),
+            ("8/8/8/8/8/8/8/8/8 w - -", Placement, 15, BadRankCount),
+            ("8/8/8/8/4x3/8/8/8 w - -", Placement, 9, UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 x - -", SideToMove, 16, UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 w KQx -", Castling, 20, UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 w KK -", Castling, 19, UnexpectedChar('K')),
+            ("8/8/8/8/8/8/8/8 w - e4", EnPassant, 20, BadSquare),
+            (
+                "8/8/8/8/8/8/8/8 w - - 1x 1",
+                HalfmoveClock,
+                23,
+                UnexpectedChar('x'),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w - - 0 -1",
+                FullmoveNumber,
+                24,
+                UnexpectedChar('-'),
+            ),
+            ("8/8/8/8/8/8/8/8 w - - 0 1 x", FullmoveNumber, 26, TrailingInput),
+            ("8/8/8/8/8/8/8/8 w - - 0 1", Placement, 0, BadKingCount),
+            ("4k3/8/8/8/8/8/8/3KK3 w - -", Placement, 0, BadKingCount),
+            ("4k3/8/8/8/8/8/8/4K3 w - e3", EnPassant, 24, BadEnPassantRank),
+            ("4k3/8/8/8/8/8/8/4K3 b - e6", EnPassant, 24, BadEnPassantRank),
+        ];
+
+        for (fen, field, index, kind) in cases {
+            assert_eq!(
+                Position::from_fen(fen),
+                Err(FenError { field, index, kind }),
+                "{fen}"
+            );
+        }
+    }
+}
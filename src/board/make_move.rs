@@ -0,0 +1,289 @@
+//! Playing and taking back moves on a Position.
+
+use super::bitboard::BitBoard;
+use super::castling::CastlingRights;
+use super::position::Position;
+use crate::moves::Move;
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// The state that a move destroys and that can't be recovered from the move
+/// itself, as returned by `Position::make_move` and needed by
+/// `Position::unmake_move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Undo {
+    pub captured:           Option<PieceType>,
+    pub en_passant_targets: BitBoard,
+    pub castling_rights:    CastlingRights,
+    pub halfmove_clock:     u32,
+}
+
+impl Position {
+    /// Plays a move, which must be legal in this position, updating the
+    /// position in place. Returns what's needed to take the move back with
+    /// `unmake_move`.
+    pub fn make_move(&mut self, m: Move) -> Undo {
+        let us = self.side_to_move();
+        let (from, to) = (m.from(), m.to());
+        let piece = self.piece_at(from).expect("no piece on the origin square");
+
+        let mut undo = Undo {
+            captured:           None,
+            en_passant_targets: self.en_passant_targets(),
+            castling_rights:    self.castling_rights(),
+            halfmove_clock:     self.halfmove_clock(),
+        };
+
+        self.set_en_passant_targets(0);
+        self.set_halfmove_clock(self.halfmove_clock() + 1);
+
+        if let Some((king_to, rook_to)) = m.castling_destinations() {
+            // The move's destination is the rook. Both pieces are lifted
+            // first, as in Chess960 either may land on the other's square.
+            let king = self.remove_piece(from).unwrap();
+            let rook = self.remove_piece(to).unwrap();
+            self.put_piece(king, king_to);
+            self.put_piece(rook, rook_to);
+        } else {
+            if m.is_capture() {
+                undo.captured = self.remove_piece(captured_square(m, us));
+                self.set_halfmove_clock(0);
+            }
+
+            self.remove_piece(from);
+            self.put_piece(m.promotion().unwrap_or(piece), to);
+
+            if piece.is_pawn() {
+                self.set_halfmove_clock(0);
+            }
+            if m.is_double_push() {
+                let skipped = (from.index() + to.index()) / 2;
+                self.set_en_passant_targets(1 << skipped);
+            }
+        }
+
+        // Moving the king or either rook, or capturing a rook on its starting
+        // square, loses the corresponding rights.
+        let mut rights = self.castling_rights();
+        if piece.is_king() {
+            rights.remove(CastlingRights::for_color(us));
+        }
+        for right in rights.iter() {
+            let rook = self.castling_rook(right);
+            if rook == from || rook == to {
+                rights.remove(right);
+            }
+        }
+        self.set_castling_rights(rights);
+
+        if us == PieceColor::Black {
+            self.set_fullmove_number(self.fullmove_number() + 1);
+        }
+        self.set_side_to_move(!us);
+
+        self.debug_assert_consistent();
+        undo
+    }
+
+    /// Takes back a move previously played with `make_move`, given the Undo
+    /// that it returned.
+    pub fn unmake_move(&mut self, m: Move, undo: Undo) {
+        let us = !self.side_to_move();
+        let (from, to) = (m.from(), m.to());
+
+        self.set_side_to_move(us);
+        if us == PieceColor::Black {
+            self.set_fullmove_number(self.fullmove_number() - 1);
+        }
+
+        if let Some((king_to, rook_to)) = m.castling_destinations() {
+            let king = self.remove_piece(king_to).unwrap();
+            let rook = self.remove_piece(rook_to).unwrap();
+            self.put_piece(king, from);
+            self.put_piece(rook, to);
+        } else {
+            let placed = self.remove_piece(to).unwrap();
+            let piece = match m.is_promotion() {
+                true => PieceType::WhitePawn.with_color(us),
+                false => placed,
+            };
+            self.put_piece(piece, from);
+
+            if let Some(captured) = undo.captured {
+                self.put_piece(captured, captured_square(m, us));
+            }
+        }
+
+        self.set_en_passant_targets(undo.en_passant_targets);
+        self.set_castling_rights(undo.castling_rights);
+        self.set_halfmove_clock(undo.halfmove_clock);
+
+        self.debug_assert_consistent();
+    }
+
+    /// Returns a copy of this position with the given move played, leaving
+    /// this one untouched.
+    pub fn with_move(&self, m: Move) -> Position {
+        let mut position = self.clone();
+        position.make_move(m);
+        position
+    }
+}
+
+/// The square of the piece captured by a move, which differs from the
+/// destination square for en passant.
+fn captured_square(m: Move, us: PieceColor) -> Square {
+    match (m.is_en_passant(), us) {
+        (false, _) => m.to(),
+        (true, PieceColor::White) => Square::from_index(m.to().index() - 8),
+        (true, PieceColor::Black) => Square::from_index(m.to().index() + 8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::generate_legal_moves;
+    use crate::moves::MoveFlag;
+
+    fn position(fen: &str) -> Position { Position::from_fen(fen).unwrap() }
+
+    #[test]
+    fn unmake_restores_every_move() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - \
+             0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ];
+
+        for fen in fens {
+            let original = position(fen);
+            for m in generate_legal_moves(&original) {
+                let mut position = original.clone();
+                let undo = position.make_move(m);
+                assert_ne!(position, original, "{m}");
+                assert_eq!(position.hash(), position.recompute_hash(), "{m}");
+                position.unmake_move(m, undo);
+                assert_eq!(position, original, "{m}");
+                assert_eq!(position.hash(), original.hash(), "{m}");
+            }
+        }
+    }
+
+    #[test]
+    fn quiet_moves_update_state() {
+        use Square::*;
+
+        let mut position = Position::starting_position();
+        position.make_move(Move::new(E2, E4, MoveFlag::DoublePush));
+        assert_eq!(position.en_passant_targets(), E3.bitboard());
+        assert_eq!(position.side_to_move(), PieceColor::Black);
+        assert_eq!(position.fullmove_number(), 1);
+
+        position.make_move(Move::new(G8, F6, MoveFlag::Quiet));
+        assert_eq!(position.en_passant_targets(), 0);
+        assert_eq!(position.halfmove_clock(), 1);
+        assert_eq!(position.fullmove_number(), 2);
+        assert_eq!(
+            position.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn en_passant_removes_the_captured_pawn() {
+        use Square::*;
+
+        let mut position = position("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        let undo = position.make_move(Move::new(E4, D3, MoveFlag::EnPassant));
+        assert_eq!(undo.captured, Some(PieceType::WhitePawn));
+        assert_eq!(position[D4], None);
+        assert_eq!(position[D3], Some(PieceType::BlackPawn));
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        use Square::*;
+
+        let mut position = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        position.make_move(Move::new(E1, H1, MoveFlag::KingCastle));
+        assert_eq!(position[G1], Some(PieceType::WhiteKing));
+        assert_eq!(position[F1], Some(PieceType::WhiteRook));
+        assert_eq!(position[H1], None);
+        assert_eq!(position.castling_rights(), CastlingRights::BLACK);
+
+        position.make_move(Move::new(E8, A8, MoveFlag::QueenCastle));
+        assert_eq!(position[C8], Some(PieceType::BlackKing));
+        assert_eq!(position[D8], Some(PieceType::BlackRook));
+        assert_eq!(position.castling_rights(), CastlingRights::NONE);
+    }
+
+    #[test]
+    fn chess960_castling_can_keep_either_piece_in_place() {
+        use Square::*;
+
+        // The king already stands on g1, and the rook on d1.
+        let mut original = position("3k4/8/8/8/8/8/8/3R2KR w KQ - 0 1");
+        original.set_castling_rook(CastlingRights::WHITE_KINGSIDE, H1);
+        original.set_castling_rook(CastlingRights::WHITE_QUEENSIDE, D1);
+
+        for (m, king, rook) in [
+            (Move::new(G1, H1, MoveFlag::KingCastle), G1, F1),
+            (Move::new(G1, D1, MoveFlag::QueenCastle), C1, D1),
+        ] {
+            let mut position = original.clone();
+            let undo = position.make_move(m);
+            assert_eq!(position[king], Some(PieceType::WhiteKing), "{m}");
+            assert_eq!(position[rook], Some(PieceType::WhiteRook), "{m}");
+            assert_eq!(position.hash(), position.recompute_hash(), "{m}");
+
+            position.unmake_move(m, undo);
+            assert_eq!(position, original, "{m}");
+        }
+    }
+
+    #[test]
+    fn rook_moves_and_captures_lose_rights() {
+        use Square::*;
+
+        let mut position = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        position.make_move(Move::new(A1, A8, MoveFlag::Capture));
+        assert_eq!(
+            position.castling_rights(),
+            CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_KINGSIDE
+        );
+        assert_eq!(position.halfmove_clock(), 0);
+    }
+
+    #[test]
+    fn promotions_replace_the_pawn() {
+        use Square::*;
+
+        let mut position = position("1r2k3/P7/8/8/8/8/8/4K3 w - - 5 40");
+        let m = Move::new_promotion(A7, B8, PieceType::WhiteKnight, true);
+        let undo = position.make_move(m);
+        assert_eq!(position[B8], Some(PieceType::WhiteKnight));
+        assert_eq!(position[A7], None);
+        assert_eq!(position.halfmove_clock(), 0);
+
+        position.unmake_move(m, undo);
+        assert_eq!(position[A7], Some(PieceType::WhitePawn));
+        assert_eq!(position[B8], Some(PieceType::BlackRook));
+        assert_eq!(position.halfmove_clock(), 5);
+    }
+
+    #[test]
+    fn with_move_leaves_the_original() {
+        use Square::*;
+
+        let original = Position::starting_position();
+        let m = Move::new(G1, F3, MoveFlag::Quiet);
+        let moved = original.with_move(m);
+
+        assert_eq!(original, Position::starting_position());
+        assert_eq!(moved[F3], Some(PieceType::WhiteKnight));
+    }
+}
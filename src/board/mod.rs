@@ -1,3 +1,8 @@
+pub mod attacks;
 pub mod bitboard;
+pub mod castling;
+pub mod fen;
+pub mod make_move;
 pub mod position;
 pub mod square;
+pub mod zobrist;
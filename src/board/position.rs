@@ -1,4 +1,6 @@
 use super::bitboard::*;
+use super::castling::{CastlingRights, STANDARD_CASTLING_ROOKS};
+use super::zobrist;
 use crate::piece::{PieceColor, PieceType};
 use crate::types::*;
 
@@ -11,19 +13,56 @@ pub struct Position {
     black:              SingleColorPosition,
     all_pieces:         BitBoard,
     en_passant_targets: BitBoard,
+    side_to_move:       PieceColor,
+    castling_rights:    CastlingRights,
+    halfmove_clock:     u32,
+    fullmove_number:    u32,
+
+    // The squares that the castling rooks started on, indexed by the bit of
+    // the corresponding CastlingRight. Stored rather than assumed to be the
+    // corners, so that Chess960 starting positions can be supported.
+    castling_rooks: [Square; 4],
+
+    // Square-indexed copy of the bitboards above, so that "what is on this
+    // square?" doesn't require checking every bitboard. Must be kept in sync.
+    mailbox: [Option<PieceType>; 64],
+
+    // Zobrist key of everything above except the clocks, updated by each
+    // setter so that it never needs recomputing.
+    hash: u64,
 }
 
 impl Position {
     /// Constructs a new Position, given SingleColorPositions for both
-    /// the white pieces and black and the current en passant targets.
+    /// the white pieces and black, the current en passant targets, the side
+    /// to move, the remaining castling rights and both move counters.
     pub const fn new(
         white: SingleColorPosition,
         black: SingleColorPosition,
         en_passant_targets: BitBoard,
+        side_to_move: PieceColor,
+        castling_rights: CastlingRights,
+        halfmove_clock: u32,
+        fullmove_number: u32,
     ) -> Self {
         let all_pieces = white.pieces | black.pieces;
+        let mailbox = build_mailbox(&white, &black);
 
-        Self { white, black, all_pieces, en_passant_targets }
+        let mut position = Self {
+            white,
+            black,
+            all_pieces,
+            en_passant_targets,
+            side_to_move,
+            castling_rights,
+            halfmove_clock,
+            fullmove_number,
+            castling_rooks: STANDARD_CASTLING_ROOKS,
+            mailbox,
+            hash: 0,
+        };
+        position.hash = zobrist::hash(&position);
+        position
     }
 
     pub const fn starting_position() -> Self {
@@ -40,13 +79,164 @@ impl Position {
             .black_rooks(RANK_8 & (FILE_A | FILE_H))
             .black_queens(RANK_8 & FILE_D)
             .black_kings(RANK_8 & FILE_E)
+            .side_to_move(PieceColor::White)
+            .castling_rights(CastlingRights::ALL)
+            .halfmove_clock(0)
+            .fullmove_number(1)
             .build()
     }
 
-    pub fn en_passant_targets(&self) -> BitBoard { self.en_passant_targets }
+    pub const fn en_passant_targets(&self) -> BitBoard {
+        self.en_passant_targets
+    }
 
     pub fn set_en_passant_targets(&mut self, targets: BitBoard) {
+        self.hash ^= zobrist::en_passant_key(self);
         self.en_passant_targets = targets;
+        self.hash ^= zobrist::en_passant_key(self);
+    }
+
+    pub const fn side_to_move(&self) -> PieceColor { self.side_to_move }
+
+    pub fn set_side_to_move(&mut self, color: PieceColor) {
+        if color != self.side_to_move {
+            self.hash ^= zobrist::en_passant_key(self) ^ zobrist::SIDE_TO_MOVE;
+            self.side_to_move = color;
+            self.hash ^= zobrist::en_passant_key(self);
+        }
+    }
+
+    pub const fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn set_castling_rights(&mut self, rights: CastlingRights) {
+        let changed = CastlingRights::from_bits(
+            self.castling_rights.bits() ^ rights.bits(),
+        );
+        self.hash ^= zobrist::castling_key(changed);
+        self.castling_rights = rights;
+    }
+
+    /// Returns the square that the rook used by the given (single) castling
+    /// right started on.
+    pub const fn castling_rook(&self, right: CastlingRights) -> Square {
+        self.castling_rooks[right.index()]
+    }
+
+    pub fn set_castling_rook(&mut self, right: CastlingRights, rook: Square) {
+        self.castling_rooks[right.index()] = rook;
+    }
+
+    /// The number of halfmoves since the last capture or pawn move.
+    pub const fn halfmove_clock(&self) -> u32 { self.halfmove_clock }
+
+    pub fn set_halfmove_clock(&mut self, halfmoves: u32) {
+        self.halfmove_clock = halfmoves;
+    }
+
+    /// The number of the current full move, starting at 1 and incremented
+    /// after each of Black's moves.
+    pub const fn fullmove_number(&self) -> u32 { self.fullmove_number }
+
+    pub fn set_fullmove_number(&mut self, number: u32) {
+        self.fullmove_number = number;
+    }
+
+    /// The Zobrist key of the position, covering the pieces, side to move,
+    /// castling rights and (only if a pawn can capture there) en passant
+    /// file, but not the clocks.
+    pub const fn hash(&self) -> u64 { self.hash }
+
+    /// Computes the Zobrist key from scratch, which should always agree with
+    /// `hash`.
+    pub const fn recompute_hash(&self) -> u64 { zobrist::hash(self) }
+
+    /// Returns the piece on the given square, if any.
+    pub const fn piece_at(&self, square: Square) -> Option<PieceType> {
+        self.mailbox[square.index() as usize]
+    }
+
+    /// Places a piece on an empty square, updating both the BitBoards and the
+    /// mailbox.
+    pub(crate) fn put_piece(&mut self, piece: PieceType, square: Square) {
+        debug_assert!(self.piece_at(square).is_none());
+        self.update_pieces(piece, square, |position, mask| {
+            let side = position.side_mut(piece.color());
+            *side.board_mut(piece) |= mask;
+            side.pieces |= mask;
+            position.all_pieces |= mask;
+            position.mailbox[square.index() as usize] = Some(piece);
+        });
+    }
+
+    /// Removes and returns the piece on the given square, updating both the
+    /// BitBoards and the mailbox.
+    pub(crate) fn remove_piece(&mut self, square: Square) -> Option<PieceType> {
+        let piece = self.piece_at(square)?;
+        self.update_pieces(piece, square, |position, mask| {
+            let side = position.side_mut(piece.color());
+            *side.board_mut(piece) &= !mask;
+            side.pieces &= !mask;
+            position.all_pieces &= !mask;
+            position.mailbox[square.index() as usize] = None;
+        });
+        Some(piece)
+    }
+
+    /// Applies a change to the given piece on the given square, toggling its
+    /// key in the hash. Moving a pawn can also change whether en passant is
+    /// possible, so that part of the key is recomputed when needed.
+    fn update_pieces(
+        &mut self,
+        piece: PieceType,
+        square: Square,
+        change: impl FnOnce(&mut Self, BitBoard),
+    ) {
+        let pawn_with_en_passant =
+            piece.is_pawn() && self.en_passant_targets != 0;
+        if pawn_with_en_passant {
+            self.hash ^= zobrist::en_passant_key(self);
+        }
+
+        change(self, square.bitboard());
+        self.hash ^= zobrist::PIECE_SQUARE[piece.index() as usize]
+            [square.index() as usize];
+
+        if pawn_with_en_passant {
+            self.hash ^= zobrist::en_passant_key(self);
+        }
+    }
+
+    fn side_mut(&mut self, color: PieceColor) -> &mut SingleColorPosition {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+
+    /// Returns true if the mailbox agrees with the BitBoards on every square,
+    /// and the stored hash is up to date.
+    pub fn is_consistent(&self) -> bool {
+        let boards_agree = PieceType::ALL.iter().all(|&piece| {
+            (0..64).all(|i| {
+                let on_board = self[piece] >> i & 1 == 1;
+                on_board == (self.mailbox[i] == Some(piece))
+            })
+        });
+        let sides_agree = self.white.pieces & self.black.pieces == 0
+            && self.white.pieces | self.black.pieces == self.all_pieces;
+
+        boards_agree && sides_agree && self.hash == self.recompute_hash()
+    }
+
+    /// Panics (in debug builds only) if the mailbox, BitBoards and hash
+    /// disagree.
+    pub fn debug_assert_consistent(&self) {
+        debug_assert!(
+            self.is_consistent(),
+            "mailbox, bitboards or hash are out of sync: {self:?}"
+        );
     }
 
     pub const fn all_pieces(&self) -> BitBoard { self.all_pieces }
@@ -67,6 +257,34 @@ impl Position {
     pub const fn black_queens(&self) -> BitBoard { self.black.queens }
     pub const fn black_kings(&self) -> BitBoard { self.black.kings }
     pub const fn black_pieces(&self) -> BitBoard { self.black.pieces }
+
+    /// Returns true if neither side has enough material left to checkmate:
+    /// king against king, king and a single minor piece against king, or
+    /// kings and any number of bishops all on squares of the same color.
+    pub const fn is_insufficient_material(&self) -> bool {
+        let white = &self.white;
+        let black = &self.black;
+        let others = white.pawns
+            | white.rooks
+            | white.queens
+            | black.pawns
+            | black.rooks
+            | black.queens;
+        if others != 0 {
+            return false;
+        }
+
+        let knights = white.knights | black.knights;
+        let bishops = white.bishops | black.bishops;
+        match (knights | bishops).count_ones() {
+            0 | 1 => true,
+            _ => {
+                knights == 0
+                    && (bishops & LIGHT_SQUARES == 0
+                        || bishops & DARK_SQUARES == 0)
+            },
+        }
+    }
 }
 
 impl std::ops::Index<PieceColor> for Position {
@@ -81,9 +299,8 @@ impl std::ops::Index<PieceColor> for Position {
 
 impl std::ops::Index<Square> for Position {
     type Output = Option<PieceType>;
-    fn index(&self, _square: Square) -> &Self::Output {
-        // TODO: implement this
-        unimplemented!()
+    fn index(&self, square: Square) -> &Self::Output {
+        &self.mailbox[square.index() as usize]
     }
 }
 
@@ -144,6 +361,50 @@ impl SingleColorPosition {
     pub const fn rooks(&self) -> &BitBoard { &self.rooks }
     pub const fn queens(&self) -> &BitBoard { &self.queens }
     pub const fn kings(&self) -> &BitBoard { &self.kings }
+
+    /// Returns every BitBoard, ordered by piece (pawns to kings).
+    const fn boards(&self) -> [BitBoard; 6] {
+        [
+            self.pawns,
+            self.knights,
+            self.bishops,
+            self.rooks,
+            self.queens,
+            self.kings,
+        ]
+    }
+
+    fn board_mut(&mut self, piece: PieceType) -> &mut BitBoard {
+        match piece.index() % 6 {
+            0 => &mut self.pawns,
+            1 => &mut self.knights,
+            2 => &mut self.bishops,
+            3 => &mut self.rooks,
+            4 => &mut self.queens,
+            _ => &mut self.kings,
+        }
+    }
+}
+
+/// Builds a square-indexed mailbox from the given per-color BitBoards.
+const fn build_mailbox(
+    white: &SingleColorPosition,
+    black: &SingleColorPosition,
+) -> [Option<PieceType>; 64] {
+    let mut mailbox = [None; 64];
+    let sides = [white.boards(), black.boards()];
+
+    let mut i = 0;
+    while i < PieceType::ALL.len() {
+        let mut board = sides[i / 6][i % 6];
+        while board != 0 {
+            mailbox[board.trailing_zeros() as usize] = Some(PieceType::ALL[i]);
+            board &= board - 1;
+        }
+        i += 1;
+    }
+
+    mailbox
 }
 
 
@@ -165,6 +426,15 @@ pub struct PositionBuilder {
     pub black_kings:   BitBoard,
 
     pub en_passant_targets: BitBoard,
+    pub side_to_move:       PieceColor,
+    pub castling_rights:    CastlingRights,
+    pub halfmove_clock:     u32,
+    pub fullmove_number:    u32,
+    pub castling_rooks:     [Square; 4],
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self { Self::new() }
 }
 
 impl PositionBuilder {
@@ -183,15 +453,26 @@ impl PositionBuilder {
             black_queens:       0,
             black_kings:        0,
             en_passant_targets: 0,
+            side_to_move:       PieceColor::White,
+            castling_rights:    CastlingRights::NONE,
+            halfmove_clock:     0,
+            fullmove_number:    1,
+            castling_rooks:     STANDARD_CASTLING_ROOKS,
         }
     }
 
     pub const fn build(&self) -> Position {
-        Position::new(
+        let mut position = Position::new(
             self.get_white_pieces(),
             self.get_black_pieces(),
             self.en_passant_targets,
-        )
+            self.side_to_move,
+            self.castling_rights,
+            self.halfmove_clock,
+            self.fullmove_number,
+        );
+        position.castling_rooks = self.castling_rooks;
+        position
     }
 
     pub const fn get_white_pieces(&self) -> SingleColorPosition {
@@ -270,12 +551,60 @@ impl PositionBuilder {
         self.en_passant_targets = targets;
         self
     }
+
+    pub const fn side_to_move(mut self, color: PieceColor) -> Self {
+        self.side_to_move = color;
+        self
+    }
+
+    pub const fn castling_rights(mut self, rights: CastlingRights) -> Self {
+        self.castling_rights = rights;
+        self
+    }
+
+    /// Sets the starting squares of the castling rooks, indexed in the same
+    /// order as the bits of a CastlingRights. Defaults to the corners.
+    pub const fn castling_rooks(mut self, rooks: [Square; 4]) -> Self {
+        self.castling_rooks = rooks;
+        self
+    }
+
+    pub const fn halfmove_clock(mut self, halfmoves: u32) -> Self {
+        self.halfmove_clock = halfmoves;
+        self
+    }
+
+    pub const fn fullmove_number(mut self, number: u32) -> Self {
+        self.fullmove_number = number;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn insufficient_material_is_detected() {
+        let insufficient =
+            |fen| Position::from_fen(fen).unwrap().is_insufficient_material();
+
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/6N1 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/5b2 w - - 0 1"));
+        // Bishops on c1 and f4 (both dark), on either side.
+        assert!(insufficient("8/8/4k3/8/5b2/3K4/8/2B5 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/5B2/3K4/8/2B5 w - - 0 1"));
+
+        // Bishops on c1 (dark) and c2 (light).
+        assert!(!insufficient("8/8/4k3/8/8/3K4/2b5/2B5 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/8/2B3N1 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/8/1n4N1 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/7p/8 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/8/7R w - - 0 1"));
+        assert!(!Position::starting_position().is_insufficient_material());
+    }
+
     #[test]
     fn starting_position_is_correct() {
         let board1 = Position::starting_position();
@@ -292,8 +621,66 @@ mod tests {
             .black_rooks(RANK_8 & (FILE_A | FILE_H))
             .black_queens(RANK_8 & FILE_D)
             .black_kings(RANK_8 & FILE_E)
+            .castling_rights(CastlingRights::ALL)
             .build();
 
         assert_eq!(board1, board2);
     }
+
+    #[test]
+    fn builder_sets_game_state() {
+        let position = PositionBuilder::new()
+            .white_kings(Square::E1.bitboard())
+            .black_kings(Square::E8.bitboard())
+            .side_to_move(PieceColor::Black)
+            .castling_rights(CastlingRights::BLACK)
+            .halfmove_clock(7)
+            .fullmove_number(42)
+            .build();
+
+        assert_eq!(position.side_to_move(), PieceColor::Black);
+        assert_eq!(position.castling_rights(), CastlingRights::BLACK);
+        assert_eq!(position.halfmove_clock(), 7);
+        assert_eq!(position.fullmove_number(), 42);
+    }
+
+    #[test]
+    fn starting_position_has_game_state() {
+        let position = Position::starting_position();
+        assert_eq!(position.side_to_move(), PieceColor::White);
+        assert_eq!(position.castling_rights(), CastlingRights::ALL);
+        assert_eq!(position.halfmove_clock(), 0);
+        assert_eq!(position.fullmove_number(), 1);
+    }
+
+    #[test]
+    fn can_index_by_square() {
+        use PieceType::*;
+        use Square::*;
+
+        let position = Position::starting_position();
+        assert_eq!(position[A1], Some(WhiteRook));
+        assert_eq!(position[E1], Some(WhiteKing));
+        assert_eq!(position[D8], Some(BlackQueen));
+        assert_eq!(position[G7], Some(BlackPawn));
+        assert_eq!(position[E4], None);
+        assert_eq!(position.piece_at(B8), Some(BlackKnight));
+        assert!(position.is_consistent());
+    }
+
+    #[test]
+    fn mailbox_follows_mutations() {
+        use PieceType::*;
+        use Square::*;
+
+        let mut position = Position::starting_position();
+        assert_eq!(position.remove_piece(E2), Some(WhitePawn));
+        position.put_piece(WhitePawn, E4);
+        assert_eq!(position.remove_piece(E2), None);
+
+        assert_eq!(position[E2], None);
+        assert_eq!(position[E4], Some(WhitePawn));
+        assert_eq!(position.white_pawns(), RANK_2 & !FILE_E | E4.bitboard());
+        assert!(position.is_consistent());
+    }
 }
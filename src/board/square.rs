@@ -17,10 +17,74 @@ pub enum Square {
 }
 
 impl Square {
-    pub fn index(self) -> u8 { self as u8 }
-    pub fn bitboard(self) -> BitBoard { 1u64 << self.index() }
+    /// Every Square, in index order.
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = {
+        use Square::*;
+        [
+            A1, B1, C1, D1, E1, F1, G1, H1,
+            A2, B2, C2, D2, E2, F2, G2, H2,
+            A3, B3, C3, D3, E3, F3, G3, H3,
+            A4, B4, C4, D4, E4, F4, G4, H4,
+            A5, B5, C5, D5, E5, F5, G5, H5,
+            A6, B6, C6, D6, E6, F6, G6, H6,
+            A7, B7, C7, D7, E7, F7, G7, H7,
+            A8, B8, C8, D8, E8, F8, G8, H8,
+        ]
+    };
+
+    /// Returns the Square with the given index. Panics if the index is not
+    /// less than 64.
+    pub const fn from_index(index: u8) -> Self { Self::ALL[index as usize] }
+
+    /// Returns the Square on the given file and rank, both counted from 0.
+    pub const fn from_file_rank(file: u8, rank: u8) -> Self {
+        Self::from_index(rank * 8 + file)
+    }
+
+    pub const fn index(self) -> u8 { self as u8 }
+    pub const fn bitboard(self) -> BitBoard { 1u64 << self.index() }
+
+    /// The file of this square, from 0 (the A-file) to 7 (the H-file).
+    pub const fn file(self) -> u8 { self.index() % 8 }
+    /// The rank of this square, from 0 (the first rank) to 7 (the eighth).
+    pub const fn rank(self) -> u8 { self.index() / 8 }
+}
+
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = (b'a' + self.file()) as char;
+        let rank = (b'1' + self.rank()) as char;
+        write!(f, "{file}{rank}")
+    }
+}
+
+impl std::str::FromStr for Square {
+    type Err = ParseSquareError;
+
+    /// Parses a square in algebraic notation, such as "e4".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Ok(Self::from_file_rank(file - b'a', rank - b'1'))
+            },
+            _ => Err(ParseSquareError),
+        }
+    }
 }
 
+/// The error returned when a string is not a valid square name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseSquareError;
+
+impl std::fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid square name")
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +108,15 @@ mod tests {
         assert_eq!(A2.bitboard(), 0x00_00_00_00_00_00_01_00);
         assert_eq!(H8.bitboard(), 0x80_00_00_00_00_00_00_00);
     }
+
+    #[test]
+    fn square_names_round_trip() {
+        for square in Square::ALL {
+            assert_eq!(square.to_string().parse(), Ok(square));
+        }
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!("i1".parse::<Square>(), Err(ParseSquareError));
+        assert_eq!("a9".parse::<Square>(), Err(ParseSquareError));
+        assert_eq!("a10".parse::<Square>(), Err(ParseSquareError));
+    }
 }
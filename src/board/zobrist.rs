@@ -0,0 +1,169 @@
+//! Zobrist hashing: a position's key is the XOR of a random number for each
+//! piece on each square, plus numbers for the side to move, each castling
+//! right and the en passant file.
+//!
+//! The numbers come from a fixed-seed SplitMix64 generator evaluated at
+//! compile time, so keys are the same across runs and platforms.
+
+use super::castling::CastlingRights;
+use super::position::Position;
+use crate::movegen::pawns::{black_pawn_attacks, white_pawn_attacks};
+use crate::piece::PieceColor;
+use crate::types::*;
+
+const SEED: u64 = 0x2b99_2ddf_a232_49d6;
+
+/// One key per piece type (by `PieceType::index`) per square.
+pub const PIECE_SQUARE: [[u64; 64]; 12] = {
+    let mut keys = [[0; 64]; 12];
+    let mut state = SEED;
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            let (next, key) = splitmix64(state);
+            state = next;
+            keys[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+    keys
+};
+
+/// XORed in when black is to move.
+pub const SIDE_TO_MOVE: u64 = splitmix64(SEED ^ 1).1;
+
+/// One key per castling right (by `CastlingRights::index`).
+pub const CASTLING: [u64; 4] = keys(SEED ^ 2);
+
+/// One key per file of the en passant target.
+pub const EN_PASSANT_FILE: [u64; 8] = keys(SEED ^ 3);
+
+/// Computes the key of the given position from scratch. Positions keep
+/// their key up to date as they change, so this is mostly useful for
+/// checking that (see `Position::recompute_hash`).
+///
+/// The en passant file is only included when a pawn of the side to move
+/// could actually capture there, so that positions which only differ by an
+/// unusable en passant target hash the same.
+pub const fn hash(position: &Position) -> u64 {
+    let mut key = 0;
+
+    let mut i = 0;
+    while i < 64 {
+        if let Some(piece) = position.piece_at(Square::from_index(i)) {
+            key ^= PIECE_SQUARE[piece.index() as usize][i as usize];
+        }
+        i += 1;
+    }
+
+    if let PieceColor::Black = position.side_to_move() {
+        key ^= SIDE_TO_MOVE;
+    }
+    key ^= castling_key(position.castling_rights());
+    key ^ en_passant_key(position)
+}
+
+/// The XOR of the keys of every right in the given set.
+pub const fn castling_key(rights: CastlingRights) -> u64 {
+    let mut key = 0;
+    let mut i = 0;
+    while i < CASTLING.len() {
+        if rights.bits() >> i & 1 == 1 {
+            key ^= CASTLING[i];
+        }
+        i += 1;
+    }
+    key
+}
+
+/// The key of the en passant file, or 0 if no pawn of the side to move can
+/// capture en passant.
+pub const fn en_passant_key(position: &Position) -> u64 {
+    let targets = position.en_passant_targets();
+    if targets == 0 {
+        return 0;
+    }
+
+    let attacks = match position.side_to_move() {
+        PieceColor::White => white_pawn_attacks(position.white_pawns()),
+        PieceColor::Black => black_pawn_attacks(position.black_pawns()),
+    };
+    match targets & attacks {
+        0 => 0,
+        target => EN_PASSANT_FILE[(target.trailing_zeros() % 8) as usize],
+    }
+}
+
+const fn keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+/// Advances a SplitMix64 state, returning the new state and its output.
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(fen: &str) -> u64 {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.hash(), hash(&position));
+        position.hash()
+    }
+
+    #[test]
+    fn transpositions_hash_the_same() {
+        use Square::*;
+
+        use crate::moves::{Move, MoveFlag};
+
+        let a = Position::starting_position()
+            .with_move(Move::new(G1, F3, MoveFlag::Quiet))
+            .with_move(Move::new(G8, F6, MoveFlag::Quiet))
+            .with_move(Move::new(B1, C3, MoveFlag::Quiet));
+        let b = Position::starting_position()
+            .with_move(Move::new(B1, C3, MoveFlag::Quiet))
+            .with_move(Move::new(G8, F6, MoveFlag::Quiet))
+            .with_move(Move::new(G1, F3, MoveFlag::Quiet));
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&Position::starting_position()));
+    }
+
+    #[test]
+    fn state_changes_the_key() {
+        let base = key("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_ne!(base, key("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"));
+        assert_ne!(base, key("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1"));
+        // The clocks aren't part of the key.
+        assert_eq!(base, key("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 12 30"));
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_capturable() {
+        assert_eq!(
+            key("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1")
+        );
+        assert_ne!(
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1")
+        );
+    }
+}
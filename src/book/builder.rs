@@ -0,0 +1,316 @@
+//! Building Polyglot books from collections of games.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::polyglot::{encode_move, Entry, Randoms};
+use crate::board::position::Position;
+use crate::game::GameResult;
+use crate::movegen::generate_legal_moves;
+use crate::moves::Move;
+use crate::piece::PieceColor;
+
+/// Accumulates book moves from whole games, then writes them out as a sorted
+/// Polyglot book.
+///
+/// Each time a move is played from a position it scores 2 if the side that
+/// played it went on to win, 1 for a draw (or an unknown result) and 0 for a
+/// loss, like Polyglot's own `make-book`. A move's weight is its total score,
+/// scaled down if needed so that every weight in a position fits in 16 bits.
+#[derive(Clone, Debug)]
+pub struct BookBuilder {
+    randoms:   Randoms,
+    max_ply:   usize,
+    min_games: u32,
+    results:   Vec<GameResult>,
+    stats:     HashMap<(u64, u16), MoveStats>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct MoveStats {
+    games: u32,
+    score: u64,
+}
+
+/// The error returned when a game contains a move that isn't legal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IllegalBookMove {
+    /// The (zero-based) ply of the move within the game.
+    pub ply: usize,
+    pub mv:  Move,
+}
+
+impl fmt::Display for IllegalBookMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal move {} at ply {}", self.mv, self.ply)
+    }
+}
+
+impl std::error::Error for IllegalBookMove {}
+
+impl BookBuilder {
+    /// Creates a builder that keys positions with the standard randoms, takes
+    /// every ply of every game and keeps every move.
+    pub fn new() -> Self {
+        Self {
+            randoms:   Randoms::standard(),
+            max_ply:   usize::MAX,
+            min_games: 1,
+            results:   vec![
+                GameResult::WhiteWins,
+                GameResult::BlackWins,
+                GameResult::Draw,
+                GameResult::Ongoing,
+            ],
+            stats:     HashMap::new(),
+        }
+    }
+
+    /// Keys positions with the given randoms instead of the standard ones.
+    pub fn randoms(mut self, randoms: Randoms) -> Self {
+        self.randoms = randoms;
+        self
+    }
+
+    /// Only takes moves from the first `plies` plies of each game.
+    pub fn max_ply(mut self, plies: usize) -> Self {
+        self.max_ply = plies;
+        self
+    }
+
+    /// Leaves out moves that were played in fewer than `games` games.
+    pub fn min_games(mut self, games: u32) -> Self {
+        self.min_games = games;
+        self
+    }
+
+    /// Only takes games that ended in one of the given results.
+    pub fn results(mut self, results: &[GameResult]) -> Self {
+        self.results = results.to_vec();
+        self
+    }
+
+    /// Replays a game from the given position, counting each of its moves.
+    /// If any move is illegal, nothing from the game is counted.
+    pub fn add_game(
+        &mut self,
+        start: &Position,
+        moves: &[Move],
+        result: GameResult,
+    ) -> Result<(), IllegalBookMove> {
+        if !self.results.contains(&result) {
+            return Ok(());
+        }
+
+        let mut position = start.clone();
+        let mut played = Vec::new();
+        for (ply, &m) in moves.iter().enumerate().take(self.max_ply) {
+            if !generate_legal_moves(&position).contains(&m) {
+                return Err(IllegalBookMove { ply, mv: m });
+            }
+
+            let key = self.randoms.key(&position);
+            let score = score(result, position.side_to_move());
+            played.push(((key, encode_move(m)), score));
+            position.make_move(m);
+        }
+
+        for (entry, score) in played {
+            let stats = self.stats.entry(entry).or_default();
+            stats.games += 1;
+            stats.score += score;
+        }
+        Ok(())
+    }
+
+    /// The book's entries, sorted by key and then by descending weight.
+    /// Moves with a weight of zero are left out, as they'd never be chosen.
+    pub fn entries(&self) -> Vec<Entry> {
+        let kept: Vec<_> = self
+            .stats
+            .iter()
+            .filter(|(_, stats)| stats.games >= self.min_games)
+            .collect();
+
+        // Weights are scaled by the best move that's kept in each position.
+        let mut max_scores = HashMap::<u64, u64>::new();
+        for &(&(key, _), stats) in &kept {
+            let max = max_scores.entry(key).or_default();
+            *max = (*max).max(stats.score);
+        }
+
+        let mut entries: Vec<_> = kept
+            .into_iter()
+            .map(|(&(key, mv), stats)| {
+                let max = max_scores[&key].max(u16::MAX as u64);
+                let weight = stats.score * u16::MAX as u64 / max;
+                Entry { key, mv, weight: weight as u16, learn: 0 }
+            })
+            .filter(|entry| entry.weight > 0)
+            .collect();
+
+        entries.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then(b.weight.cmp(&a.weight))
+                .then(a.mv.cmp(&b.mv))
+        });
+        entries
+    }
+
+    /// Writes the book in Polyglot's binary format.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for entry in self.entries() {
+            writer.write_all(&entry.to_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the book to a file at the given path, replacing any file
+    /// already there.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write(io::BufWriter::new(std::fs::File::create(path)?))
+    }
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self { Self::new() }
+}
+
+/// What a move scores for the side that played it, given the game's result.
+fn score(result: GameResult, mover: PieceColor) -> u64 {
+    match (result, mover) {
+        (GameResult::WhiteWins, PieceColor::White)
+        | (GameResult::BlackWins, PieceColor::Black) => 2,
+        (GameResult::WhiteWins, PieceColor::Black)
+        | (GameResult::BlackWins, PieceColor::White) => 0,
+        (GameResult::Draw | GameResult::Ongoing, _) => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::book::polyglot::Book;
+    use crate::moves::MoveFlag;
+    use crate::types::*;
+
+    fn read(builder: &BookBuilder) -> Book<Cursor<Vec<u8>>> {
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        Book::new(Cursor::new(bytes)).unwrap()
+    }
+
+    fn games() -> [(Vec<Move>, GameResult); 3] {
+        use Square::*;
+
+        let e4 = Move::new(E2, E4, MoveFlag::DoublePush);
+        let d4 = Move::new(D2, D4, MoveFlag::DoublePush);
+        let e5 = Move::new(E7, E5, MoveFlag::DoublePush);
+        let c5 = Move::new(C7, C5, MoveFlag::DoublePush);
+        [
+            (vec![e4, e5], GameResult::WhiteWins),
+            (vec![e4, c5], GameResult::BlackWins),
+            (vec![d4], GameResult::Draw),
+        ]
+    }
+
+    #[test]
+    fn moves_are_weighted_by_result() {
+        use Square::*;
+
+        let mut builder = BookBuilder::new();
+        let start = Position::starting_position();
+        for (moves, result) in games() {
+            builder.add_game(&start, &moves, result).unwrap();
+        }
+
+        let entries = builder.entries();
+        assert!(entries.windows(2).all(|pair| pair[0].key <= pair[1].key));
+
+        // 1. e4 scored 2 + 0 and 1. d4 scored 1.
+        let mut book = read(&builder);
+        let moves = book.moves(&start).unwrap();
+        assert_eq!(moves, [
+            (Move::new(E2, E4, MoveFlag::DoublePush), 2),
+            (Move::new(D2, D4, MoveFlag::DoublePush), 1),
+        ]);
+
+        // 1... e5 lost, so only 1... c5 is kept.
+        let after_e4 = start.with_move(Move::new(E2, E4, MoveFlag::DoublePush));
+        assert_eq!(
+            book.best_move(&after_e4).unwrap(),
+            Some(Move::new(C7, C5, MoveFlag::DoublePush))
+        );
+        assert_eq!(book.moves(&after_e4).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn weights_are_scaled_to_fit() {
+        let mut builder = BookBuilder::new();
+        let stats = |score| MoveStats { games: 1, score };
+        builder.stats.insert((1, 1), stats(80_000));
+        builder.stats.insert((1, 2), stats(40_000));
+        builder.stats.insert((2, 1), stats(3));
+
+        let weights: Vec<_> =
+            builder.entries().iter().map(|entry| entry.weight).collect();
+        assert_eq!(weights, [u16::MAX, u16::MAX / 2, 3]);
+    }
+
+    #[test]
+    fn weights_are_scaled_to_the_moves_kept() {
+        let mut builder = BookBuilder::new().min_games(2);
+        let stats = |games, score| MoveStats { games, score };
+        builder.stats.insert((1, 1), stats(1, 100_000));
+        builder.stats.insert((1, 2), stats(2, 80_000));
+        builder.stats.insert((1, 3), stats(2, 40_000));
+
+        let weights: Vec<_> =
+            builder.entries().iter().map(|entry| entry.weight).collect();
+        assert_eq!(weights, [u16::MAX, u16::MAX / 2]);
+    }
+
+    #[test]
+    fn games_are_filtered() {
+        let start = Position::starting_position();
+
+        let mut builder = BookBuilder::new().max_ply(1);
+        for (moves, result) in games() {
+            builder.add_game(&start, &moves, result).unwrap();
+        }
+        assert_eq!(builder.entries().len(), 2);
+
+        let mut builder = BookBuilder::new().min_games(2);
+        for (moves, result) in games() {
+            builder.add_game(&start, &moves, result).unwrap();
+        }
+        assert_eq!(builder.entries().len(), 1);
+
+        let mut builder = BookBuilder::new().results(&[GameResult::Draw]);
+        for (moves, result) in games() {
+            builder.add_game(&start, &moves, result).unwrap();
+        }
+        assert_eq!(builder.entries().len(), 1);
+    }
+
+    #[test]
+    fn illegal_games_are_rejected() {
+        use Square::*;
+
+        let mut builder = BookBuilder::new();
+        let moves = [
+            Move::new(E2, E4, MoveFlag::DoublePush),
+            Move::new(E2, E4, MoveFlag::DoublePush),
+        ];
+        let error = builder
+            .add_game(&Position::starting_position(), &moves, GameResult::Draw)
+            .unwrap_err();
+        assert_eq!(error, IllegalBookMove { ply: 1, mv: moves[1] });
+        assert!(builder.entries().is_empty());
+    }
+}
@@ -0,0 +1,4 @@
+//! Opening books.
+
+pub mod builder;
+pub mod polyglot;
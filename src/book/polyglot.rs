@@ -0,0 +1,487 @@
+//! Reading Polyglot (`.bin`) opening books.
+//!
+//! A Polyglot book is a list of 16-byte big-endian entries, sorted by
+//! position key: the key (8 bytes), a move (2), a weight (2) and a learning
+//! value (4) that we ignore. Keys are computed from the position with a fixed
+//! table of 781 random numbers, published as part of the format and included
+//! here as `RANDOM64`, so keys agree with every other Polyglot tool. Another
+//! table can be supplied as `Randoms` for books built with one.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::board::bitboard::*;
+use crate::board::position::Position;
+use crate::movegen::generate_legal_moves;
+use crate::movegen::pawns::{black_pawn_attacks, white_pawn_attacks};
+use crate::moves::Move;
+use crate::piece::{PieceColor, PieceType};
+
+mod random64;
+
+pub use random64::RANDOM64;
+
+/// The number of random numbers used to compute a key.
+pub const RANDOM_COUNT: usize = 781;
+
+/// The size of a book entry in bytes.
+pub const ENTRY_SIZE: usize = 16;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/// The random numbers that Polyglot keys are made of.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Randoms(Box<[u64; RANDOM_COUNT]>);
+
+impl Randoms {
+    pub fn new(randoms: [u64; RANDOM_COUNT]) -> Self { Self(Box::new(randoms)) }
+
+    /// The standard table, `RANDOM64`.
+    pub fn standard() -> Self { Self::new(RANDOM64) }
+
+    /// Reads the table from its binary form: 781 big-endian `u64`s.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() != RANDOM_COUNT * 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} bytes of randoms", RANDOM_COUNT * 8),
+            ));
+        }
+
+        let mut randoms = [0; RANDOM_COUNT];
+        for (random, chunk) in randoms.iter_mut().zip(bytes.chunks_exact(8)) {
+            *random = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Ok(Self::new(randoms))
+    }
+
+    /// Reads the table from a file in the form accepted by `from_bytes`.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Computes the Polyglot key of the given position with these randoms.
+    pub fn key(&self, position: &Position) -> u64 {
+        key_with(&self.0, position)
+    }
+}
+
+impl Default for Randoms {
+    fn default() -> Self { Self::standard() }
+}
+
+impl std::fmt::Debug for Randoms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Randoms").finish_non_exhaustive()
+    }
+}
+
+/// Computes the Polyglot key of the given position with the standard randoms.
+pub fn key(position: &Position) -> u64 { key_with(&RANDOM64, position) }
+
+/// Computes a Polyglot key from the given randoms.
+///
+/// Like our own Zobrist key, the en passant file only counts when a pawn of
+/// the side to move stands ready to capture there.
+fn key_with(randoms: &[u64; RANDOM_COUNT], position: &Position) -> u64 {
+    let mut key = 0;
+
+    for square in position.all_pieces().squares() {
+        let piece = position.piece_at(square).unwrap();
+        key ^= randoms[64 * piece_kind(piece) + square.index() as usize];
+    }
+
+    // Polyglot orders the rights white kingside, white queenside, black
+    // kingside, black queenside, which matches ours.
+    for right in position.castling_rights().iter() {
+        key ^= randoms[CASTLING_OFFSET + right.index()];
+    }
+
+    let us = position.side_to_move();
+    let attacks = match us {
+        PieceColor::White => white_pawn_attacks(position.white_pawns()),
+        PieceColor::Black => black_pawn_attacks(position.black_pawns()),
+    };
+    let en_passant = position.en_passant_targets() & attacks;
+    if en_passant != 0 {
+        let file = en_passant.trailing_zeros() as usize % 8;
+        key ^= randoms[EN_PASSANT_OFFSET + file];
+    }
+
+    if us == PieceColor::White {
+        key ^= randoms[TURN_OFFSET];
+    }
+
+    key
+}
+
+/// Polyglot numbers pieces black pawn, white pawn, black knight, and so on.
+fn piece_kind(piece: PieceType) -> usize {
+    let kind = piece.index() as usize % 6;
+    match piece.color() {
+        PieceColor::White => 2 * kind + 1,
+        PieceColor::Black => 2 * kind,
+    }
+}
+
+/// A single book entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key:    u64,
+    /// The move in Polyglot's encoding; see `decode_move`.
+    pub mv:     u16,
+    pub weight: u16,
+    pub learn:  u32,
+}
+
+impl Entry {
+    pub fn from_bytes(bytes: [u8; ENTRY_SIZE]) -> Self {
+        Self {
+            key:    u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv:     u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn:  u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// Finds the legal move in the given position that a Polyglot move stands
+/// for, if there is one.
+///
+/// Polyglot packs a move as to-file, to-rank, from-file, from-rank (three
+/// bits each, from the bottom) and a promotion piece (knight 1 to queen 4).
+/// Castling is written as the king capturing its own rook.
+pub fn decode_move(position: &Position, mv: u16) -> Option<Move> {
+    generate_legal_moves(position).into_iter().find(|&m| encode_move(m) == mv)
+}
+
+/// Encodes a move in Polyglot's format. Our castling moves are already
+/// written as the king taking its rook.
+pub fn encode_move(m: Move) -> u16 {
+    let promotion = match m.promotion() {
+        Some(piece) => piece.index() as u16 % 6,
+        None => 0,
+    };
+
+    promotion << 12 | (m.from().index() as u16) << 6 | m.to().index() as u16
+}
+
+/// A Polyglot book, searched in place rather than loaded into memory.
+#[derive(Debug)]
+pub struct Book<R> {
+    reader:  R,
+    len:     u64,
+    randoms: Randoms,
+}
+
+impl Book<BufReader<File>> {
+    /// Opens the book file at the given path, keyed with the standard
+    /// randoms.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> Book<R> {
+    /// Wraps a reader over a book's contents, which must be a whole number of
+    /// entries sorted by key.
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::with_randoms(reader, Randoms::standard())
+    }
+
+    /// Like `new`, for a book keyed with a table other than the standard one.
+    pub fn with_randoms(mut reader: R, randoms: Randoms) -> io::Result<Self> {
+        let bytes = reader.seek(SeekFrom::End(0))?;
+        if bytes % ENTRY_SIZE as u64 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "book size is not a multiple of the entry size",
+            ));
+        }
+        Ok(Self { reader, len: bytes / ENTRY_SIZE as u64, randoms })
+    }
+
+    /// The number of entries in the book.
+    pub fn len(&self) -> u64 { self.len }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    pub fn randoms(&self) -> &Randoms { &self.randoms }
+
+    fn entry(&mut self, index: u64) -> io::Result<Entry> {
+        let mut bytes = [0; ENTRY_SIZE];
+        self.reader.seek(SeekFrom::Start(index * ENTRY_SIZE as u64))?;
+        self.reader.read_exact(&mut bytes)?;
+        Ok(Entry::from_bytes(bytes))
+    }
+
+    /// Returns every entry with the given key, in book order.
+    pub fn entries(&mut self, key: u64) -> io::Result<Vec<Entry>> {
+        // Binary search for the first entry with the key.
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.entry(mid)?.key < key {
+                true => low = mid + 1,
+                false => high = mid,
+            }
+        }
+
+        let mut entries = Vec::new();
+        for index in low..self.len {
+            let entry = self.entry(index)?;
+            if entry.key != key {
+                break;
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Returns the book's moves for the given position with their weights.
+    /// Entries whose moves aren't legal in the position are skipped.
+    pub fn moves(
+        &mut self,
+        position: &Position,
+    ) -> io::Result<Vec<(Move, u16)>> {
+        let key = self.randoms.key(position);
+        Ok(self
+            .entries(key)?
+            .into_iter()
+            .filter_map(|entry| {
+                Some((decode_move(position, entry.mv)?, entry.weight))
+            })
+            .collect())
+    }
+
+    /// Returns the book move with the highest weight, taking the first in
+    /// book order on ties.
+    pub fn best_move(
+        &mut self,
+        position: &Position,
+    ) -> io::Result<Option<Move>> {
+        let moves = self.moves(position)?;
+        let best = moves.iter().rev().max_by_key(|&&(_, weight)| weight);
+        Ok(best.map(|&(m, _)| m))
+    }
+
+    /// Picks a book move at random, in proportion to the weights. `random`
+    /// should be uniformly distributed over all `u64`s, so that the choice of
+    /// random number generator is left to the caller.
+    ///
+    /// Moves with zero weight are never chosen.
+    pub fn weighted_move(
+        &mut self,
+        position: &Position,
+        random: u64,
+    ) -> io::Result<Option<Move>> {
+        let moves = self.moves(position)?;
+        let total: u64 = moves.iter().map(|&(_, weight)| weight as u64).sum();
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut pick = random % total;
+        for (m, weight) in moves {
+            if pick < weight as u64 {
+                return Ok(Some(m));
+            }
+            pick -= weight as u64;
+        }
+        unreachable!("pick is less than the total weight")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::moves::MoveFlag;
+    use crate::types::*;
+    use crate::xorshift::Xorshift;
+
+    /// Stand-in randoms, for checking that a custom table is used.
+    fn randoms() -> Randoms {
+        let mut rng = Xorshift::new(0x1234_5678_9abc_def1);
+        Randoms::new(std::array::from_fn(|_| rng.next_u64()))
+    }
+
+    fn book(mut entries: Vec<Entry>) -> Book<Cursor<Vec<u8>>> {
+        entries.sort_by_key(|entry| entry.key);
+        let bytes = entries.iter().flat_map(|entry| entry.to_bytes()).collect();
+        Book::new(Cursor::new(bytes)).unwrap()
+    }
+
+    fn entry(position: &Position, m: Move, weight: u16) -> Entry {
+        Entry { key: key(position), mv: encode_move(m), weight, learn: 0 }
+    }
+
+    #[test]
+    fn key_layout() {
+        let position =
+            Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let expected = RANDOM64[64 * 11 + 4]
+            ^ RANDOM64[64 * 10 + 60]
+            ^ RANDOM64[64 * 7 + 7]
+            ^ RANDOM64[CASTLING_OFFSET]
+            ^ RANDOM64[TURN_OFFSET];
+        assert_eq!(key(&position), expected);
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_capturable() {
+        let key = |fen| key(&Position::from_fen(fen).unwrap());
+        assert_eq!(
+            key("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1")
+        );
+        assert_eq!(
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1"),
+            key("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1")
+                ^ RANDOM64[EN_PASSANT_OFFSET + 4]
+        );
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        use Square::*;
+
+        let position =
+            Position::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let short = Move::new(E1, H1, MoveFlag::KingCastle);
+        let long = Move::new(E1, A1, MoveFlag::QueenCastle);
+        let promotion =
+            Move::new_promotion(B7, A8, PieceType::WhiteKnight, true);
+
+        // King takes rook: e1h1 and e1a1.
+        assert_eq!(encode_move(short), 7 | 4 << 6);
+        assert_eq!(encode_move(long), 4 << 6);
+        assert_eq!(encode_move(promotion), 1 << 12 | 49 << 6 | 56);
+
+        for m in generate_legal_moves(&position) {
+            assert_eq!(decode_move(&position, encode_move(m)), Some(m));
+        }
+        // e1e2 is fine, but there's nothing on d4 to move.
+        assert_eq!(decode_move(&position, 27 << 6 | 35), None);
+    }
+
+    #[test]
+    fn entries_are_found() {
+        use Square::*;
+
+        let start = Position::starting_position();
+        let e4 = Move::new(E2, E4, MoveFlag::DoublePush);
+        let d4 = Move::new(D2, D4, MoveFlag::DoublePush);
+        let after_e4 = start.with_move(e4);
+        let e5 = Move::new(E7, E5, MoveFlag::DoublePush);
+
+        let mut book = book(vec![
+            entry(&start, e4, 3),
+            entry(&after_e4, e5, 1),
+            entry(&start, d4, 5),
+        ]);
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.moves(&start).unwrap().len(), 2);
+        assert_eq!(book.moves(&after_e4).unwrap(), [(e5, 1)]);
+        assert!(book.moves(&after_e4.with_move(e5)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn moves_are_selected_by_weight() {
+        use Square::*;
+
+        let start = Position::starting_position();
+        let e4 = Move::new(E2, E4, MoveFlag::DoublePush);
+        let d4 = Move::new(D2, D4, MoveFlag::DoublePush);
+        let c4 = Move::new(C2, C4, MoveFlag::DoublePush);
+
+        let mut book = book(vec![
+            entry(&start, e4, 3),
+            entry(&start, d4, 5),
+            entry(&start, c4, 0),
+        ]);
+        assert_eq!(book.best_move(&start).unwrap(), Some(d4));
+
+        let mut picks = Vec::new();
+        for random in 0..8 {
+            picks.push(book.weighted_move(&start, random).unwrap().unwrap());
+        }
+        assert_eq!(picks.iter().filter(|&&m| m == e4).count(), 3);
+        assert_eq!(picks.iter().filter(|&&m| m == d4).count(), 5);
+
+        let mut empty = self::book(vec![]);
+        assert_eq!(empty.weighted_move(&start, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_books_are_rejected() {
+        let result = Book::new(Cursor::new(vec![0; 20]));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn custom_randoms_are_used() {
+        use Square::*;
+
+        let start = Position::starting_position();
+        let e4 = Move::new(E2, E4, MoveFlag::DoublePush);
+        let entry = Entry {
+            key:    randoms().key(&start),
+            mv:     encode_move(e4),
+            weight: 1,
+            learn:  0,
+        };
+        assert_ne!(entry.key, key(&start));
+
+        let bytes = entry.to_bytes().to_vec();
+        let mut book =
+            Book::with_randoms(Cursor::new(bytes), randoms()).unwrap();
+        assert_eq!(book.moves(&start).unwrap(), [(e4, 1)]);
+
+        let bytes = entry.to_bytes().to_vec();
+        let mut book = Book::new(Cursor::new(bytes)).unwrap();
+        assert!(book.moves(&start).unwrap().is_empty());
+    }
+
+    /// The test positions from the Polyglot book format's specification.
+    #[test]
+    fn standard_keys() {
+        let moves_and_keys = [
+            ("", 0x463b96181691fc9c),
+            ("e2e4", 0x823c9b50fd114196),
+            ("e2e4 d7d5", 0x0756b94461c50fb0),
+            ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+            ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+        ];
+
+        for (moves, expected) in moves_and_keys {
+            let mut position = Position::starting_position();
+            for uci in moves.split_whitespace() {
+                let m = generate_legal_moves(&position)
+                    .into_iter()
+                    .find(|m| m.to_string() == uci)
+                    .unwrap();
+                position.make_move(m);
+            }
+            assert_eq!(key(&position), expected, "after {moves:?}");
+            assert_eq!(Randoms::default().key(&position), expected);
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Games of chess: a starting position, the moves played from it and how the
+//! game ended.
+
+use std::fmt;
+
+use crate::board::make_move::Undo;
+use crate::board::position::Position;
+use crate::movegen::generate_legal_moves;
+use crate::movegen::legal::CheckInfo;
+use crate::moves::Move;
+use crate::piece::PieceColor;
+
+/// The result of a game, as recorded in PGN.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is still in progress, or its result isn't known (`*`).
+    #[default]
+    Ongoing,
+}
+
+impl GameResult {
+    /// The result of a game won by the given side.
+    pub const fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => GameResult::WhiteWins,
+            PieceColor::Black => GameResult::BlackWins,
+        }
+    }
+
+    /// Parses a result in the form written by `Display`.
+    pub fn from_pgn(result: &str) -> Option<Self> {
+        match result {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Ongoing),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        })
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    DrawAgreement,
+    /// Neither side can possibly checkmate. Automatic.
+    InsufficientMaterial,
+    /// The same position occurred for the third time. Must be claimed.
+    ThreefoldRepetition,
+    /// The same position occurred for the fifth time. Automatic.
+    FivefoldRepetition,
+    /// Fifty moves by each side without a capture or pawn move. Must be
+    /// claimed.
+    FiftyMoveRule,
+    /// Seventy-five moves by each side without a capture or pawn move.
+    /// Automatic.
+    SeventyFiveMoveRule,
+}
+
+impl Termination {
+    /// Returns true for draws that only end the game if a player claims them.
+    pub const fn is_claimable(self) -> bool {
+        matches!(
+            self,
+            Termination::ThreefoldRepetition | Termination::FiftyMoveRule
+        )
+    }
+}
+
+/// The error returned when trying to play a move that isn't legal, either
+/// because of the position or because the game is already over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IllegalMove {
+    pub mv: Move,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal move {}", self.mv)
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+/// A game: the position it started from and the moves played since, with a
+/// cursor into those moves so that they can be undone and redone.
+#[derive(Clone, Debug)]
+pub struct Game {
+    initial:  Position,
+    position: Position,
+
+    // Every move played, including any that have been undone but not yet
+    // redone or replaced. Only the first `undos.len()` have been played on
+    // `position`, and those have their Undos.
+    moves: Vec<Move>,
+    undos: Vec<Undo>,
+
+    // The hash of the initial position and after each move up to the
+    // cursor, for detecting repetitions.
+    hashes: Vec<u64>,
+
+    result:      GameResult,
+    termination: Option<Termination>,
+}
+
+impl Game {
+    /// Starts a game from the given position.
+    pub fn new(position: Position) -> Self {
+        let mut game = Self {
+            initial: position.clone(),
+            moves: Vec::new(),
+            undos: Vec::new(),
+            hashes: vec![position.hash()],
+            position,
+            result: GameResult::Ongoing,
+            termination: None,
+        };
+        game.update_result();
+        game
+    }
+
+    pub fn initial_position(&self) -> &Position { &self.initial }
+
+    /// The position at the cursor.
+    pub fn position(&self) -> &Position { &self.position }
+
+    /// The moves played up to the cursor.
+    pub fn moves(&self) -> &[Move] { &self.moves[..self.ply()] }
+
+    /// The number of moves played up to the cursor.
+    pub fn ply(&self) -> usize { self.undos.len() }
+
+    pub fn result(&self) -> GameResult { self.result }
+
+    /// Why the game ended, if it has.
+    pub fn termination(&self) -> Option<Termination> { self.termination }
+
+    pub fn is_over(&self) -> bool { self.result != GameResult::Ongoing }
+
+    /// Plays a move at the cursor, replacing any moves that had been undone.
+    /// The game is left untouched if the move isn't legal.
+    pub fn play(&mut self, m: Move) -> Result<(), IllegalMove> {
+        if self.is_over() || !generate_legal_moves(&self.position).contains(&m)
+        {
+            return Err(IllegalMove { mv: m });
+        }
+
+        self.moves.truncate(self.ply());
+        self.moves.push(m);
+        self.make_move(m);
+        Ok(())
+    }
+
+    pub fn can_undo(&self) -> bool { self.ply() > 0 }
+    pub fn can_redo(&self) -> bool { self.ply() < self.moves.len() }
+
+    /// Takes back the move before the cursor, returning it. It can be played
+    /// again with `redo` until another move is played in its place.
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.undos.pop()?;
+        let m = self.moves[self.ply()];
+        self.position.unmake_move(m, undo);
+        self.hashes.pop();
+        self.update_result();
+        Some(m)
+    }
+
+    /// Plays the move after the cursor again, returning it.
+    pub fn redo(&mut self) -> Option<Move> {
+        let m = *self.moves.get(self.ply())?;
+        self.make_move(m);
+        Some(m)
+    }
+
+    fn make_move(&mut self, m: Move) {
+        self.undos.push(self.position.make_move(m));
+        self.hashes.push(self.position.hash());
+        self.update_result();
+    }
+
+    /// The number of times the position at the cursor has occurred in the
+    /// game so far, counting this time.
+    ///
+    /// Positions are compared by hash, which covers the side to move,
+    /// castling rights and (capturable) en passant file as the rules
+    /// require. Only positions since the last capture or pawn move can
+    /// repeat.
+    pub fn repetitions(&self) -> usize {
+        let current = self.position.hash();
+        let reversible = self.position.halfmove_clock() as usize + 1;
+        let start = self.hashes.len().saturating_sub(reversible);
+        self.hashes[start..]
+            .iter()
+            .rev()
+            .step_by(2)
+            .filter(|&&hash| hash == current)
+            .count()
+    }
+
+    /// Returns the draw that the side to move could claim at the cursor, if
+    /// any: threefold repetition or the fifty-move rule.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.is_over() {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.position.halfmove_clock() >= 100 {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Claims a draw if one is available, returning whether the game ended.
+    pub fn claim_draw(&mut self) -> bool {
+        match self.claimable_draw() {
+            Some(termination) => {
+                self.end(GameResult::Draw, termination);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Ends the game with a win for the opponent of the given side.
+    pub fn resign(&mut self, color: PieceColor) {
+        self.end(GameResult::win_for(!color), Termination::Resignation);
+    }
+
+    /// Ends the game in a draw by agreement.
+    pub fn agree_draw(&mut self) {
+        self.end(GameResult::Draw, Termination::DrawAgreement);
+    }
+
+    fn end(&mut self, result: GameResult, termination: Termination) {
+        if !self.is_over() {
+            self.result = result;
+            self.termination = Some(termination);
+        }
+    }
+
+    /// Sets the result from the position at the cursor, including the draws
+    /// that don't need claiming. Any resignation, agreement or claim is
+    /// forgotten, as it belonged to a position that's been left.
+    fn update_result(&mut self) {
+        (self.result, self.termination) = (GameResult::Ongoing, None);
+
+        // Checkmate takes precedence over the automatic draws, even on the
+        // move that would otherwise trigger them.
+        if generate_legal_moves(&self.position).is_empty() {
+            let us = self.position.side_to_move();
+            if CheckInfo::new(&self.position).in_check() {
+                self.end(GameResult::win_for(!us), Termination::Checkmate);
+            } else {
+                self.end(GameResult::Draw, Termination::Stalemate);
+            }
+        } else if self.position.is_insufficient_material() {
+            self.end(GameResult::Draw, Termination::InsufficientMaterial);
+        } else if self.repetitions() >= 5 {
+            self.end(GameResult::Draw, Termination::FivefoldRepetition);
+        } else if self.position.halfmove_clock() >= 150 {
+            self.end(GameResult::Draw, Termination::SeventyFiveMoveRule);
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self { Self::new(Position::starting_position()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveFlag;
+    use crate::types::*;
+
+    fn fools_mate() -> [Move; 4] {
+        use Square::*;
+        [
+            Move::new(F2, F3, MoveFlag::Quiet),
+            Move::new(E7, E5, MoveFlag::DoublePush),
+            Move::new(G2, G4, MoveFlag::DoublePush),
+            Move::new(D8, H4, MoveFlag::Quiet),
+        ]
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut game = Game::default();
+        for m in fools_mate() {
+            assert_eq!(game.result(), GameResult::Ongoing);
+            game.play(m).unwrap();
+        }
+
+        assert_eq!(game.result(), GameResult::BlackWins);
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+        assert_eq!(game.moves(), fools_mate());
+    }
+
+    #[test]
+    fn stalemate_is_a_draw() {
+        use Square::*;
+
+        let position = Position::from_fen("k7/8/1Q6/8/8/8/8/K7 w - - 0 1");
+        let mut game = Game::new(position.unwrap());
+        game.play(Move::new(B6, C7, MoveFlag::Quiet)).unwrap();
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.termination(), Some(Termination::Stalemate));
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        use Square::*;
+
+        let mut game = Game::default();
+        let m = Move::new(E2, E5, MoveFlag::Quiet);
+        assert_eq!(game.play(m), Err(IllegalMove { mv: m }));
+        assert_eq!(game.ply(), 0);
+        assert_eq!(game.position(), &Position::starting_position());
+
+        for m in fools_mate() {
+            game.play(m).unwrap();
+        }
+        let m = Move::new(E1, F2, MoveFlag::Quiet);
+        assert_eq!(game.play(m), Err(IllegalMove { mv: m }));
+    }
+
+    #[test]
+    fn moves_can_be_undone_and_redone() {
+        use Square::*;
+
+        let mut game = Game::default();
+        for m in fools_mate() {
+            game.play(m).unwrap();
+        }
+
+        assert_eq!(game.undo(), Some(fools_mate()[3]));
+        assert_eq!(game.undo(), Some(fools_mate()[2]));
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.moves(), &fools_mate()[..2]);
+        assert!(game.can_redo());
+
+        assert_eq!(game.redo(), Some(fools_mate()[2]));
+        assert_eq!(game.ply(), 3);
+
+        // Playing a different move drops the rest.
+        game.play(Move::new(D8, F6, MoveFlag::Quiet)).unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.redo(), None);
+
+        while game.undo().is_some() {}
+        assert_eq!(game.position(), game.initial_position());
+        assert_eq!(game.undo(), None);
+    }
+
+    /// Shuffles both knights out and back, repeating the starting position.
+    fn shuffle(game: &mut Game) {
+        use Square::*;
+        for (from, to) in [(G1, F3), (G8, F6), (F3, G1), (F6, G8)] {
+            game.play(Move::new(from, to, MoveFlag::Quiet)).unwrap();
+        }
+    }
+
+    #[test]
+    fn repetitions_are_counted() {
+        let mut game = Game::default();
+        assert_eq!(game.repetitions(), 1);
+
+        shuffle(&mut game);
+        assert_eq!(game.repetitions(), 2);
+        assert_eq!(game.claimable_draw(), None);
+
+        shuffle(&mut game);
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(
+            game.claimable_draw(),
+            Some(Termination::ThreefoldRepetition)
+        );
+        assert!(!game.is_over());
+
+        shuffle(&mut game);
+        shuffle(&mut game);
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.termination(), Some(Termination::FivefoldRepetition));
+
+        game.undo();
+        assert!(!game.is_over());
+        assert!(game.claim_draw());
+        assert_eq!(game.termination(), Some(Termination::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn repetitions_need_the_same_rights() {
+        use Square::*;
+
+        // The first time round, White could still castle.
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        let mut game = Game::new(position.unwrap());
+        for _ in 0..2 {
+            for (from, to) in [(H1, H2), (E8, E7), (H2, H1), (E7, E8)] {
+                game.play(Move::new(from, to, MoveFlag::Quiet)).unwrap();
+            }
+        }
+        assert_eq!(game.repetitions(), 2);
+    }
+
+    #[test]
+    fn move_rules_apply() {
+        use Square::*;
+
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 99 80";
+        let mut game = Game::new(Position::from_fen(fen).unwrap());
+        assert_eq!(game.claimable_draw(), None);
+        game.play(Move::new(A1, A2, MoveFlag::Quiet)).unwrap();
+        assert_eq!(game.claimable_draw(), Some(Termination::FiftyMoveRule));
+        assert!(Termination::FiftyMoveRule.is_claimable());
+
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 149 100";
+        let mut game = Game::new(Position::from_fen(fen).unwrap());
+        game.play(Move::new(A1, A2, MoveFlag::Quiet)).unwrap();
+        assert_eq!(game.termination(), Some(Termination::SeventyFiveMoveRule));
+
+        // Unless the last move mates.
+        let fen = "6k1/5ppp/8/8/8/8/8/R3K3 w - - 149 100";
+        let mut game = Game::new(Position::from_fen(fen).unwrap());
+        game.play(Move::new(A1, A8, MoveFlag::Quiet)).unwrap();
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+    }
+
+    #[test]
+    fn dead_positions_are_drawn() {
+        use Square::*;
+
+        let fen = "8/8/4k3/8/8/3K4/8/1n4N1 w - - 0 1";
+        let mut game = Game::new(Position::from_fen(fen).unwrap());
+        assert!(!game.is_over());
+        game.play(Move::new(G1, E2, MoveFlag::Quiet)).unwrap();
+        game.play(Move::new(B1, D2, MoveFlag::Quiet)).unwrap();
+        game.play(Move::new(E2, C3, MoveFlag::Quiet)).unwrap();
+        game.play(Move::new(D2, B3, MoveFlag::Quiet)).unwrap();
+        assert!(!game.is_over());
+
+        let fen = "8/8/4k3/8/8/3K4/2n5/6N1 w - - 0 1";
+        let mut game = Game::new(Position::from_fen(fen).unwrap());
+        assert!(!game.is_over());
+        game.play(Move::new(D3, C2, MoveFlag::Capture)).unwrap();
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.termination(), Some(Termination::InsufficientMaterial));
+        assert!(!Termination::InsufficientMaterial.is_claimable());
+    }
+
+    #[test]
+    fn resignation_and_agreement() {
+        use Square::*;
+
+        let mut game = Game::default();
+        game.resign(PieceColor::White);
+        assert_eq!(game.result(), GameResult::BlackWins);
+        assert_eq!(game.termination(), Some(Termination::Resignation));
+
+        let m = Move::new(E2, E4, MoveFlag::DoublePush);
+        assert!(game.play(m).is_err());
+
+        let mut game = Game::default();
+        game.play(m).unwrap();
+        game.agree_draw();
+        assert_eq!(game.result(), GameResult::Draw);
+        // Going back to before the agreement reopens the game.
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+}
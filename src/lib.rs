@@ -1,8 +1,15 @@
 #![allow(dead_code)]
 
 pub mod board;
+pub mod book;
+pub mod game;
 pub mod movegen;
+pub mod moves;
+pub mod notation;
+pub mod pgn;
 pub mod piece;
+pub mod see;
+mod xorshift;
 
 mod types {
     pub use super::board::square::Square;
@@ -10,6 +17,7 @@ mod types {
 
 pub mod prelude {
     pub use crate::board::square::Square;
+    pub use crate::moves::{Move, MoveFlag};
 }
 
 pub fn add(left: usize, right: usize) -> usize { left + right }
@@ -0,0 +1,192 @@
+//! Castling move generation.
+//!
+//! The castling rooks' starting squares are read from the position rather
+//! than assumed to be in the corners, and the rules are applied in their
+//! general (Chess960) form: the king always ends on the g- or c-file and the
+//! rook beside it, every square either piece crosses must be empty, and the
+//! king must not start on, pass through or land on an attacked square.
+//!
+//! Moves are generated as the king taking its own rook; see `Move`.
+
+use super::rays::between;
+use super::MoveList;
+use crate::board::bitboard::*;
+use crate::board::castling::CastlingRights;
+use crate::board::position::Position;
+use crate::moves::{Move, MoveFlag};
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// Adds every legal castling move for the side to move to the given list.
+pub fn add_castling_moves(position: &Position, moves: &mut MoveList) {
+    let us = position.side_to_move();
+    let rights = position.castling_rights() & CastlingRights::for_color(us);
+    let kings = position[PieceType::WhiteKing.with_color(us)];
+    if rights.is_empty() || kings.count_ones() != 1 {
+        return;
+    }
+
+    let king = Square::from_index(kings.trailing_zeros() as u8);
+    let back_rank = match us {
+        PieceColor::White => 0,
+        PieceColor::Black => 7,
+    };
+    if king.rank() != back_rank {
+        return;
+    }
+
+    let sides = [
+        (CastlingRights::kingside(us), MoveFlag::KingCastle),
+        (CastlingRights::queenside(us), MoveFlag::QueenCastle),
+    ];
+
+    for (right, flag) in sides {
+        if !rights.contains(right) {
+            continue;
+        }
+
+        let rook = position.castling_rook(right);
+        if position[rook] != Some(PieceType::WhiteRook.with_color(us)) {
+            continue;
+        }
+
+        let m = Move::new(king, rook, flag);
+        let (king_to, rook_to) = m.castling_destinations().unwrap();
+
+        // The king and rook may pass over each other's squares, but nothing
+        // else may be in the way.
+        let movers = king.bitboard() | rook.bitboard();
+        let crossed = span(king, king_to) | span(rook, rook_to);
+        if crossed & position.all_pieces() & !movers != 0 {
+            continue;
+        }
+
+        // Ignore the king and rook themselves, so that neither can shield a
+        // square that will be exposed once they've moved.
+        let occupancy = position.all_pieces() & !movers;
+        let attacked = span(king, king_to).squares().any(|square| {
+            position.attackers_to(square, occupancy) & position[!us] != 0
+        });
+        if attacked {
+            continue;
+        }
+
+        moves.push(m);
+    }
+}
+
+/// The squares from one square to another on the same rank, inclusive.
+fn span(from: Square, to: Square) -> BitBoard {
+    between(from, to) | from.bitboard() | to.bitboard()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn castling_moves(fen: &str) -> MoveList {
+        let mut moves = MoveList::new();
+        add_castling_moves(&Position::from_fen(fen).unwrap(), &mut moves);
+        moves
+    }
+
+    #[test]
+    fn both_sides_can_castle() {
+        use Square::*;
+
+        let moves = castling_moves("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&Move::new(E1, H1, MoveFlag::KingCastle)));
+        assert!(moves.contains(&Move::new(E1, A1, MoveFlag::QueenCastle)));
+
+        let moves = castling_moves("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        assert!(moves.contains(&Move::new(E8, H8, MoveFlag::KingCastle)));
+        assert!(moves.contains(&Move::new(E8, A8, MoveFlag::QueenCastle)));
+    }
+
+    #[test]
+    fn rights_are_required() {
+        assert!(castling_moves("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1").is_empty());
+        assert_eq!(
+            castling_moves("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn path_must_be_empty() {
+        assert!(castling_moves("4k3/8/8/8/8/8/8/RN2K1NR w KQ - 0 1").is_empty());
+        // The b-file square only needs to be empty for the rook.
+        assert!(castling_moves("4k3/8/8/8/8/8/8/1R2K3 w Q - 0 1").is_empty());
+    }
+
+    #[test]
+    fn king_must_not_cross_attacked_squares() {
+        use Square::*;
+
+        // In check.
+        assert!(castling_moves("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").is_empty());
+        // Passing through f1.
+        assert_eq!(castling_moves("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1")[..], [
+            Move::new(E1, A1, MoveFlag::QueenCastle)
+        ]);
+        // Landing on c1.
+        assert_eq!(castling_moves("2r3k1/8/8/8/8/8/8/R3K2R w KQ - 0 1")[..], [
+            Move::new(E1, H1, MoveFlag::KingCastle)
+        ]);
+        // Only the rook passes through b1, so that's fine.
+        assert_eq!(
+            castling_moves("1r4k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rook_squares_come_from_the_position() {
+        use Square::*;
+
+        // A Chess960 setup with the king on b1 and rooks on a1 and g1.
+        let mut position =
+            Position::from_fen("1k6/8/8/8/8/8/8/RK4R1 w KQ - 0 1").unwrap();
+        position.set_castling_rook(CastlingRights::WHITE_KINGSIDE, G1);
+        position.set_castling_rook(CastlingRights::WHITE_QUEENSIDE, A1);
+
+        let mut moves = MoveList::new();
+        add_castling_moves(&position, &mut moves);
+        assert!(moves.contains(&Move::new(B1, G1, MoveFlag::KingCastle)));
+        assert!(moves.contains(&Move::new(B1, A1, MoveFlag::QueenCastle)));
+
+        // The rook's destination (f1) must be free as well.
+        position.put_piece(PieceType::WhiteKnight, F1);
+        let mut moves = MoveList::new();
+        add_castling_moves(&position, &mut moves);
+        assert!(!moves.contains(&Move::new(B1, G1, MoveFlag::KingCastle)));
+    }
+
+    #[test]
+    fn castles_never_share_squares_with_king_moves() {
+        use Square::*;
+
+        // b1c1 is an ordinary king move here, and the king castling kingside
+        // from g1 doesn't move at all.
+        for (fen, kingside, queenside) in [
+            ("1k6/8/8/8/8/8/8/RK4R1 w KQ - 0 1", G1, A1),
+            ("1k6/8/8/8/8/8/8/R5KR w KQ - 0 1", H1, A1),
+        ] {
+            let mut position = Position::from_fen(fen).unwrap();
+            position
+                .set_castling_rook(CastlingRights::WHITE_KINGSIDE, kingside);
+            position
+                .set_castling_rook(CastlingRights::WHITE_QUEENSIDE, queenside);
+
+            let moves = crate::movegen::generate_legal_moves(&position);
+            assert_eq!(moves.iter().filter(|m| m.is_castle()).count(), 2);
+            for m in moves.iter() {
+                let same_squares = moves.iter().filter(|other| {
+                    other.from() == m.from() && other.to() == m.to()
+                });
+                assert_eq!(same_squares.count(), 1, "{m:?} in {fen}");
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::board::bitboard::*;
+use crate::types::*;
+
+/// Finds every square attacked by the kings represented by the given BitBoard.
+pub const fn all_king_attacks(kings: BitBoard) -> BitBoard {
+    let row = kings | (kings << 1 & !FILE_A) | (kings >> 1 & !FILE_H);
+    (row | row << 8 | row >> 8) & !kings
+}
+
+/// Precomputed king attacks for each square.
+const KING_ATTACKS: [BitBoard; 64] = {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        table[i] = all_king_attacks(1 << i);
+        i += 1;
+    }
+    table
+};
+
+/// Finds every square attacked by a king on the given square.
+pub const fn king_attacks(square: Square) -> BitBoard {
+    KING_ATTACKS[square.index() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn king_attacks_do_not_wrap() {
+        use Square::*;
+
+        assert_eq!(
+            king_attacks(A1),
+            A2.bitboard() | B1.bitboard() | B2.bitboard()
+        );
+        assert_eq!(king_attacks(H5).count_ones(), 5);
+        assert_eq!(king_attacks(E4).count_ones(), 8);
+        assert_eq!(king_attacks(H8).count_ones(), 3);
+    }
+
+    #[test]
+    fn set_attacks_cover_both_kings() {
+        use Square::*;
+
+        let kings = E1.bitboard() | E8.bitboard();
+        assert_eq!(
+            all_king_attacks(kings),
+            king_attacks(E1) | king_attacks(E8)
+        );
+    }
+}
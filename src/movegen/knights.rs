@@ -0,0 +1,61 @@
+use crate::board::bitboard::*;
+use crate::types::*;
+
+/// Finds every square attacked by the knights represented by the given
+/// BitBoard.
+pub const fn all_knight_attacks(knights: BitBoard) -> BitBoard {
+    let not_a = !FILE_A;
+    let not_h = !FILE_H;
+    let not_ab = !(FILE_A | FILE_B);
+    let not_gh = !(FILE_G | FILE_H);
+
+    (knights << 17 & not_a)
+        | (knights << 15 & not_h)
+        | (knights << 10 & not_ab)
+        | (knights << 6 & not_gh)
+        | (knights >> 17 & not_h)
+        | (knights >> 15 & not_a)
+        | (knights >> 10 & not_gh)
+        | (knights >> 6 & not_ab)
+}
+
+/// Precomputed knight attacks for each square.
+const KNIGHT_ATTACKS: [BitBoard; 64] = {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        table[i] = all_knight_attacks(1 << i);
+        i += 1;
+    }
+    table
+};
+
+/// Finds every square attacked by a knight on the given square.
+pub const fn knight_attacks(square: Square) -> BitBoard {
+    KNIGHT_ATTACKS[square.index() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knight_attacks_do_not_wrap() {
+        use Square::*;
+
+        assert_eq!(knight_attacks(A1), B3.bitboard() | C2.bitboard());
+        assert_eq!(knight_attacks(H8), G6.bitboard() | F7.bitboard());
+        assert_eq!(knight_attacks(D4).count_ones(), 8);
+        assert_eq!(knight_attacks(G2).count_ones(), 4);
+        assert_eq!(knight_attacks(B7).count_ones(), 4);
+    }
+
+    #[test]
+    fn set_attacks_are_union_of_single_attacks() {
+        let knights = RANK_1 & (FILE_B | FILE_G) | CENTER_4;
+        let expected = knights
+            .squares()
+            .fold(EMPTY, |attacks, square| attacks | knight_attacks(square));
+        assert_eq!(all_knight_attacks(knights), expected);
+    }
+}
@@ -0,0 +1,317 @@
+//! Strictly legal move generation.
+//!
+//! Rather than playing each move and seeing whether the king is left in check,
+//! the checkers and pinned pieces are found up front and used to mask the
+//! potential moves.
+
+use super::rays::{between, line};
+use super::sliders::{bishop_attacks, rook_attacks};
+use super::{generate_moves_with, MoveList, Promotions};
+use crate::board::bitboard::*;
+use crate::board::position::Position;
+use crate::moves::Move;
+use crate::piece::PieceColor;
+use crate::piece::PieceType::*;
+use crate::types::*;
+
+/// Generates every legal move for the side to move. A side without a king
+/// (which `Position::from_fen` rejects, but a `PositionBuilder` can make) has
+/// none.
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    generate_legal_moves_with(position, Promotions::All)
+}
+
+/// Like `generate_legal_moves`, but only promotes pawns to the given pieces.
+pub fn generate_legal_moves_with(
+    position: &Position,
+    promotions: Promotions,
+) -> MoveList {
+    let king = position[WhiteKing.with_color(position.side_to_move())];
+    if king == EMPTY {
+        return MoveList::new();
+    }
+
+    let checks = CheckInfo::new(position);
+    generate_moves_with(position, promotions)
+        .into_iter()
+        .filter(|&m| checks.is_legal(position, m))
+        .collect()
+}
+
+/// The checks and pins against the side to move's king.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckInfo {
+    pub king:       Square,
+    /// Enemy pieces giving check.
+    pub checkers:   BitBoard,
+    /// Friendly pieces that can't leave the line between an enemy slider and
+    /// the king.
+    pub pinned:     BitBoard,
+    /// Squares that a non-king move must land on to resolve any check: the
+    /// checker and the squares between it and the king. Full if not in check,
+    /// and empty in double check.
+    pub check_mask: BitBoard,
+}
+
+impl CheckInfo {
+    /// Finds the checks and pins in the position. Panics if the side to move
+    /// has no king.
+    pub fn new(position: &Position) -> Self {
+        let us = position.side_to_move();
+        let them = !us;
+        let king = Square::from_index(
+            position[WhiteKing.with_color(us)].trailing_zeros() as u8,
+        );
+        let occupancy = position.all_pieces();
+
+        let checkers = position.attackers_to(king, occupancy) & position[them];
+        let check_mask = match checkers.count_ones() {
+            0 => FULL,
+            1 => {
+                let checker =
+                    Square::from_index(checkers.trailing_zeros() as u8);
+                checkers | between(king, checker)
+            },
+            _ => EMPTY,
+        };
+
+        // Enemy sliders that would attack the king if nothing were in the way.
+        let queens = position[WhiteQueen.with_color(them)];
+        let snipers = (rook_attacks(king, EMPTY)
+            & (position[WhiteRook.with_color(them)] | queens))
+            | (bishop_attacks(king, EMPTY)
+                & (position[WhiteBishop.with_color(them)] | queens));
+
+        let mut pinned = EMPTY;
+        for sniper in snipers.squares() {
+            let blockers = between(king, sniper) & occupancy;
+            if blockers.count_ones() == 1 {
+                pinned |= blockers & position[us];
+            }
+        }
+
+        Self { king, checkers, pinned, check_mask }
+    }
+
+    pub fn in_check(&self) -> bool { self.checkers != 0 }
+
+    /// The squares a piece on the given square may move to without exposing
+    /// its king: the line through it and the king if pinned, else anywhere.
+    pub fn pin_ray(&self, square: Square) -> BitBoard {
+        if self.pinned & square.bitboard() != 0 {
+            line(self.king, square)
+        } else {
+            FULL
+        }
+    }
+
+    /// Checks whether a potential move (as returned by `generate_moves`) is
+    /// legal.
+    pub fn is_legal(&self, position: &Position, m: Move) -> bool {
+        let (from, to) = (m.from(), m.to());
+        let them = !position.side_to_move();
+
+        // Castling is only generated if the king's path is safe.
+        if m.is_castle() {
+            return true;
+        }
+
+        if from == self.king {
+            // The king mustn't be able to hide behind itself from a slider.
+            let occupancy = position.all_pieces() ^ from.bitboard();
+            return position.attackers_to(to, occupancy) & position[them] == 0;
+        }
+
+        if m.is_en_passant() {
+            return self.is_legal_en_passant(position, m);
+        }
+
+        let allowed = self.check_mask & self.pin_ray(from);
+        allowed & to.bitboard() != 0
+    }
+
+    /// En passant removes two pieces from the capturer's path at once (e.g.
+    /// both pawns from the king's rank), so the usual pin and check masks
+    /// aren't enough. Instead, look for sliders that would see the king.
+    fn is_legal_en_passant(&self, position: &Position, m: Move) -> bool {
+        let us = position.side_to_move();
+        let them = !us;
+        let captured = match us {
+            PieceColor::White => m.to().bitboard().dec_rank(),
+            PieceColor::Black => m.to().bitboard().inc_rank(),
+        };
+
+        let occupancy = position.all_pieces() ^ m.from().bitboard() ^ captured
+            | m.to().bitboard();
+        let queens = position[WhiteQueen.with_color(them)];
+        let rooks = position[WhiteRook.with_color(them)] | queens;
+        let bishops = position[WhiteBishop.with_color(them)] | queens;
+        let king = self.king;
+
+        // Checks from anything but the captured pawn must still be resolved.
+        let other_checkers = self.checkers & !captured;
+        if other_checkers & !(rooks | bishops) != 0 {
+            return false;
+        }
+
+        rook_attacks(king, occupancy) & rooks == 0
+            && bishop_attacks(king, occupancy) & bishops == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::PositionBuilder;
+    use crate::moves::MoveFlag;
+
+    fn legal_moves(fen: &str) -> MoveList {
+        generate_legal_moves(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn counts_match_known_positions() {
+        assert_eq!(
+            generate_legal_moves(&Position::starting_position()).len(),
+            20
+        );
+        assert_eq!(
+            legal_moves("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -").len(),
+            14
+        );
+        assert_eq!(
+            legal_moves(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w \
+                 KQkq -"
+            )
+            .len(),
+            48
+        );
+        assert_eq!(
+            legal_moves(
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - \
+                 0 1"
+            )
+            .len(),
+            6
+        );
+        assert_eq!(
+            legal_moves(
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"
+            )
+            .len(),
+            44
+        );
+    }
+
+    #[test]
+    fn positions_without_a_king_have_no_moves() {
+        let position = PositionBuilder::new()
+            .white_pawns(0x1000)
+            .black_pawns(0x10_0000_0000_0000)
+            .build();
+        assert!(generate_legal_moves(&position).is_empty());
+    }
+
+    #[test]
+    fn king_cannot_step_into_check() {
+        use Square::*;
+
+        // The king may not retreat along the rook's ray, even though the
+        // square behind it currently looks unattacked.
+        let moves = legal_moves("4k3/8/8/8/8/8/4K3/r7 w - - 0 1");
+        assert!(!moves.contains(&Move::new(E2, E1, MoveFlag::Quiet)));
+        assert!(!moves.contains(&Move::new(E2, D1, MoveFlag::Quiet)));
+        assert!(moves.contains(&Move::new(E2, E3, MoveFlag::Quiet)));
+
+        // The rook checks along the rank, so the king can't slide away along
+        // it either.
+        let moves = legal_moves("4k3/8/8/8/8/8/r3K3/8 w - - 0 1");
+        assert!(!moves.contains(&Move::new(E2, F2, MoveFlag::Quiet)));
+        assert!(moves.contains(&Move::new(E2, E3, MoveFlag::Quiet)));
+        assert_eq!(moves.len(), 6);
+    }
+
+    #[test]
+    fn pinned_pieces_stay_on_the_pin_ray() {
+        use Square::*;
+
+        let moves = legal_moves("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert!(moves.contains(&Move::new(E2, E8, MoveFlag::Capture)));
+        assert!(moves.contains(&Move::new(E2, E5, MoveFlag::Quiet)));
+        assert!(!moves.contains(&Move::new(E2, D2, MoveFlag::Quiet)));
+
+        // A pinned knight can never move.
+        let moves = legal_moves("6k1/8/8/b7/8/2N5/8/4K3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.from() != C3));
+    }
+
+    #[test]
+    fn checks_must_be_resolved() {
+        use Square::*;
+
+        // Capture the checker, block, or move the king.
+        let moves = legal_moves("4k3/8/8/b7/8/8/8/RN2K3 w - - 0 1");
+        let expected = [
+            Move::new(A1, A5, MoveFlag::Capture),
+            Move::new(B1, C3, MoveFlag::Quiet),
+            Move::new(B1, D2, MoveFlag::Quiet),
+            Move::new(E1, D1, MoveFlag::Quiet),
+            Move::new(E1, F1, MoveFlag::Quiet),
+            Move::new(E1, E2, MoveFlag::Quiet),
+            Move::new(E1, F2, MoveFlag::Quiet),
+        ];
+        assert_eq!(moves.len(), expected.len());
+        for m in expected {
+            assert!(moves.contains(&m), "{m:?}");
+        }
+    }
+
+    #[test]
+    fn double_check_allows_only_king_moves() {
+        use Square::*;
+
+        let moves = legal_moves("4k3/8/8/8/1b6/8/8/R3K1r1 w - - 0 1");
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.from() == E1));
+    }
+
+    #[test]
+    fn en_passant_cannot_expose_king_along_rank() {
+        use Square::*;
+
+        let moves = legal_moves("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+        assert!(!moves.contains(&Move::new(B5, C6, MoveFlag::EnPassant)));
+        assert!(moves.contains(&Move::new(B5, B6, MoveFlag::Quiet)));
+
+        let moves = legal_moves("8/8/8/KPp3k1/8/8/8/8 w - c6 0 1");
+        assert!(moves.contains(&Move::new(B5, C6, MoveFlag::EnPassant)));
+    }
+
+    #[test]
+    fn promotions_can_resolve_checks() {
+        use Square::*;
+
+        use crate::piece::PieceType::*;
+
+        // Four ways to promote by capturing the checking rook.
+        let moves = legal_moves("2r1k3/1P6/8/8/8/8/8/2K5 w - - 0 1");
+        assert_eq!(moves.iter().filter(|m| m.is_promotion()).count(), 4);
+        assert!(moves.contains(&Move::new_promotion(B7, C8, WhiteQueen, true)));
+
+        let position =
+            Position::from_fen("2r1k3/1P6/8/8/8/8/8/2K5 w - - 0 1").unwrap();
+        let moves =
+            generate_legal_moves_with(&position, Promotions::QueenAndKnight);
+        assert_eq!(moves.iter().filter(|m| m.is_promotion()).count(), 2);
+    }
+
+    #[test]
+    fn en_passant_can_capture_the_checker() {
+        use Square::*;
+
+        let moves = legal_moves("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        assert!(moves.contains(&Move::new(E4, D3, MoveFlag::EnPassant)));
+        assert!(!moves.contains(&Move::new(E4, E3, MoveFlag::Quiet)));
+    }
+}
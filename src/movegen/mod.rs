@@ -1,13 +1,140 @@
+pub mod castling;
+pub mod king;
+pub mod knights;
+pub mod legal;
+mod move_list;
 pub mod pawns;
+pub mod perft;
+pub mod rays;
+pub mod sliders;
 
+pub use legal::{generate_legal_moves, generate_legal_moves_with};
+pub use move_list::MoveList;
+
+use self::king::king_attacks;
+use self::knights::knight_attacks;
+use self::sliders::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::board::bitboard::*;
 use crate::board::position::Position;
-use crate::piece::{Piece, PieceType};
+use crate::moves::{Move, MoveFlag};
+use crate::piece::PieceType;
+use crate::types::*;
+
+/// Which pieces pawns may promote to during move generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Promotions {
+    /// Queens, rooks, bishops and knights.
+    #[default]
+    All,
+    /// Only queens and knights. A rook or bishop promotion is almost never
+    /// better than a queen promotion, so searches can usually skip them.
+    QueenAndKnight,
+}
+
+impl Promotions {
+    /// The (white) pieces that pawns may promote to, most valuable first.
+    pub const fn pieces(self) -> &'static [PieceType] {
+        use PieceType::*;
+        match self {
+            Promotions::All => {
+                &[WhiteQueen, WhiteRook, WhiteBishop, WhiteKnight]
+            },
+            Promotions::QueenAndKnight => &[WhiteQueen, WhiteKnight],
+        }
+    }
+}
 
-fn gen_moves_for_piece(position: &Position, piece: Piece) {
+/// Generates every potential move for the side to move. Moves that would leave
+/// the mover's own king in check are included.
+pub fn generate_moves(position: &Position) -> MoveList {
+    generate_moves_with(position, Promotions::All)
+}
+
+/// Like `generate_moves`, but only promotes pawns to the given pieces.
+pub fn generate_moves_with(
+    position: &Position,
+    promotions: Promotions,
+) -> MoveList {
     use PieceType::*;
 
-    match piece.piece_type {
-        WhitePawn => pawns::white_pawn_moves(piece.square.bitboard(), position),
-        _ => 0,
-    };
+    let mut moves = MoveList::new();
+    let us = position.side_to_move();
+    let occupancy = position.all_pieces();
+
+    pawns::add_pawn_moves(position, &mut moves, promotions);
+
+    for from in position[WhiteKnight.with_color(us)].squares() {
+        add_piece_moves(position, &mut moves, from, knight_attacks(from));
+    }
+    for from in position[WhiteBishop.with_color(us)].squares() {
+        let attacks = bishop_attacks(from, occupancy);
+        add_piece_moves(position, &mut moves, from, attacks);
+    }
+    for from in position[WhiteRook.with_color(us)].squares() {
+        let attacks = rook_attacks(from, occupancy);
+        add_piece_moves(position, &mut moves, from, attacks);
+    }
+    for from in position[WhiteQueen.with_color(us)].squares() {
+        let attacks = queen_attacks(from, occupancy);
+        add_piece_moves(position, &mut moves, from, attacks);
+    }
+    for from in position[WhiteKing.with_color(us)].squares() {
+        add_piece_moves(position, &mut moves, from, king_attacks(from));
+    }
+    castling::add_castling_moves(position, &mut moves);
+
+    moves
+}
+
+/// Adds a move from the given square to each attacked square that isn't
+/// occupied by a friendly piece.
+fn add_piece_moves(
+    position: &Position,
+    moves: &mut MoveList,
+    from: Square,
+    attacks: BitBoard,
+) {
+    let us = position.side_to_move();
+    let targets = attacks & !position[us];
+
+    for to in (targets & position[!us]).squares() {
+        moves.push(Move::new(from, to, MoveFlag::Capture));
+    }
+    for to in (targets & position.empty_squares()).squares() {
+        moves.push(Move::new(from, to, MoveFlag::Quiet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_has_twenty_moves() {
+        let moves = generate_moves(&Position::starting_position());
+        assert_eq!(moves.len(), 20);
+
+        let mut position = Position::starting_position();
+        position.set_side_to_move(crate::piece::PieceColor::Black);
+        assert_eq!(generate_moves(&position).len(), 20);
+    }
+
+    #[test]
+    fn every_piece_type_is_generated() {
+        use Square::*;
+
+        let position =
+            Position::from_fen("4k3/4p1p1/8/8/3B4/2N5/4Q3/R3K3 w - - 0 1")
+                .unwrap();
+        let moves = generate_moves(&position);
+
+        assert!(moves.contains(&Move::new(D4, G7, MoveFlag::Capture)));
+        assert!(moves.contains(&Move::new(E2, E7, MoveFlag::Capture)));
+        assert!(moves.contains(&Move::new(C3, B5, MoveFlag::Quiet)));
+        assert!(moves.contains(&Move::new(A1, A8, MoveFlag::Quiet)));
+        assert!(moves.contains(&Move::new(E1, F1, MoveFlag::Quiet)));
+        assert!(!moves.contains(&Move::new(A1, E1, MoveFlag::Quiet)));
+        assert!(!moves.contains(&Move::new(C3, E2, MoveFlag::Quiet)));
+        assert!(!moves.contains(&Move::new(D4, H8, MoveFlag::Quiet)));
+    }
 }
@@ -0,0 +1,145 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::moves::Move;
+
+/// A fixed-capacity list of moves, stored inline so that move generation never
+/// allocates. No legal chess position has more than 218 moves, so the capacity
+/// of 256 is never exceeded in practice.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MoveList::CAPACITY],
+    len:   usize,
+}
+
+impl MoveList {
+    pub const CAPACITY: usize = 256;
+
+    pub const fn new() -> Self {
+        Self { moves: [Move::NULL; Self::CAPACITY], len: 0 }
+    }
+
+    /// Appends a move to the list. Panics if the list is already full.
+    pub fn push(&mut self, m: Move) {
+        self.moves[self.len] = m;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) { self.len = 0; }
+
+    pub fn as_slice(&self) -> &[Move] { &self.moves[..self.len] }
+    pub fn as_mut_slice(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self { Self::new() }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+    fn deref(&self) -> &Self::Target { self.as_slice() }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target { self.as_mut_slice() }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool { self.as_slice() == other.as_slice() }
+}
+
+impl Eq for MoveList {}
+
+impl Extend<Move> for MoveList {
+    fn extend<T: IntoIterator<Item = Move>>(&mut self, iter: T) {
+        for m in iter {
+            self.push(m);
+        }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<T: IntoIterator<Item = Move>>(iter: T) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = IntoIter;
+    fn into_iter(self) -> Self::IntoIter { IntoIter { list: self, next: 0 } }
+}
+
+/// Owning iterator over a MoveList.
+#[derive(Clone, Debug)]
+pub struct IntoIter {
+    list: MoveList,
+    next: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.list.get(self.next).copied()?;
+        self.next += 1;
+        Some(m)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveFlag;
+    use crate::types::*;
+
+    #[test]
+    fn can_push_and_iterate() {
+        use Square::*;
+
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+
+        let e4 = Move::new(E2, E4, MoveFlag::DoublePush);
+        let nf3 = Move::new(G1, F3, MoveFlag::Quiet);
+        list.push(e4);
+        list.push(nf3);
+
+        assert_eq!(list.len(), 2);
+        assert!(list.contains(&nf3));
+        assert!(!list.contains(&Move::new(G1, H3, MoveFlag::Quiet)));
+
+        list.sort_by_key(|m| m.from().index());
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), [nf3, e4]);
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), [nf3, e4]);
+    }
+
+    #[test]
+    fn can_hold_capacity() {
+        let list: MoveList =
+            std::iter::repeat_n(Move::NULL, MoveList::CAPACITY).collect();
+        assert_eq!(list.len(), MoveList::CAPACITY);
+    }
+}
@@ -1,5 +1,88 @@
+use super::{MoveList, Promotions};
 use crate::board::bitboard::*;
 use crate::board::position::Position;
+use crate::moves::{Move, MoveFlag};
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// Adds every potential move for the side to move's pawns to the given list,
+/// including double pushes, en passant captures and promotions to the given
+/// set of pieces.
+pub fn add_pawn_moves(
+    position: &Position,
+    moves: &mut MoveList,
+    promotions: Promotions,
+) {
+    let us = position.side_to_move();
+    let pawns = position[PieceType::WhitePawn.with_color(us)];
+    let empty = position.empty_squares();
+    let enemies = position[!us];
+
+    // Squares directly in front of each pawn, and how far that is in indices.
+    let (ahead, forward, double_push_rank, en_passant_rank, last_rank) =
+        match us {
+            PieceColor::White => (pawns.inc_rank(), 8, RANK_4, RANK_6, RANK_8),
+            PieceColor::Black => (pawns.dec_rank(), -8, RANK_5, RANK_3, RANK_1),
+        };
+    let en_passant = position.en_passant_targets() & en_passant_rank;
+
+    let single_pushes = ahead & empty;
+    let double_pushes = match us {
+        PieceColor::White => single_pushes.inc_rank(),
+        PieceColor::Black => single_pushes.dec_rank(),
+    } & double_push_rank
+        & empty;
+
+    add_shifted(moves, single_pushes & !last_rank, forward, MoveFlag::Quiet);
+    add_shifted(moves, double_pushes, 2 * forward, MoveFlag::DoublePush);
+    add_promotions(
+        moves,
+        single_pushes & last_rank,
+        forward,
+        false,
+        promotions,
+    );
+
+    for (targets, delta) in
+        [(ahead.inc_file(), forward + 1), (ahead.dec_file(), forward - 1)]
+    {
+        let captures = targets & enemies;
+        add_shifted(moves, captures & !last_rank, delta, MoveFlag::Capture);
+        add_shifted(moves, targets & en_passant, delta, MoveFlag::EnPassant);
+        add_promotions(moves, captures & last_rank, delta, true, promotions);
+    }
+}
+
+/// Like `add_shifted`, but adds a promotion to each of the given pieces for
+/// every target square.
+fn add_promotions(
+    moves: &mut MoveList,
+    targets: BitBoard,
+    delta: i8,
+    capture: bool,
+    promotions: Promotions,
+) {
+    for to in targets.squares() {
+        let from = Square::from_index((to.index() as i8 - delta) as u8);
+        for &piece in promotions.pieces() {
+            moves.push(Move::new_promotion(from, to, piece, capture));
+        }
+    }
+}
+
+/// Adds a move to each of the given target squares, from the square `delta`
+/// indices behind it.
+fn add_shifted(
+    moves: &mut MoveList,
+    targets: BitBoard,
+    delta: i8,
+    flag: MoveFlag,
+) {
+    for to in targets.squares() {
+        let from = Square::from_index((to.index() as i8 - delta) as u8);
+        moves.push(Move::new(from, to, flag));
+    }
+}
 
 /// Finds all potential moves for white pawns represented by the given
 /// BitBoard, within the context of the current position. Does not account for
@@ -49,13 +132,15 @@ fn black_pawn_pushes(
     one_square | two_squares
 }
 
-fn white_pawn_attacks(white_pawns: BitBoard) -> BitBoard {
+/// Returns the squares attacked by the given white pawns.
+pub const fn white_pawn_attacks(white_pawns: BitBoard) -> BitBoard {
     let diag_right = (white_pawns << 9) & !FILE_A;
     let diag_left = (white_pawns << 7) & !FILE_H;
     diag_right | diag_left
 }
 
-fn black_pawn_attacks(black_pawns: BitBoard) -> BitBoard {
+/// Returns the squares attacked by the given black pawns.
+pub const fn black_pawn_attacks(black_pawns: BitBoard) -> BitBoard {
     let diag_left = (black_pawns >> 9) & !FILE_H;
     let diag_right = (black_pawns >> 7) & !FILE_A;
     diag_right | diag_left
@@ -81,61 +166,60 @@ mod tests {
     use super::*;
     use crate::board::position::PositionBuilder;
 
-    const DUMMY_WHITE_PAWNS: BitBoard = 0x00_00_00_08_20_00_11_00;
-    const DUMMY_BLACK_PAWNS: BitBoard = 0x00_0c_00_20_00_82_00_00;
-
-    const DUMMY_POSITION: Position = PositionBuilder::new()
-        .white_pawns(DUMMY_WHITE_PAWNS)
-        .black_pawns(DUMMY_BLACK_PAWNS)
-        .build();
+    fn dummy_position() -> Position {
+        Position::from_fen("7k/2pp4/8/3P1p2/5P2/1p5p/P3P3/7K w - - 0 1")
+            .unwrap()
+    }
 
     #[test]
     fn white_pawns_can_push() {
-        let white_pawn_pushes = white_pawn_pushes(
-            DUMMY_POSITION.white_pawns(),
-            DUMMY_POSITION.empty_squares(),
-        );
+        let position = dummy_position();
+        let white_pawn_pushes =
+            white_pawn_pushes(position.white_pawns(), position.empty_squares());
         assert_eq!(white_pawn_pushes, 0x80011110000);
     }
 
     #[test]
     fn white_pawns_can_capture() {
+        let position = dummy_position();
         let white_pawn_captures = white_pawn_captures(
-            DUMMY_POSITION.white_pawns(),
-            DUMMY_POSITION.black_pieces(),
+            position.white_pawns(),
+            position.black_pieces(),
         );
         assert_eq!(white_pawn_captures, 0x20000);
     }
 
     #[test]
     fn white_pawns_can_move() {
+        let position = dummy_position();
         let white_pawn_moves =
-            white_pawn_moves(DUMMY_POSITION.white_pawns(), &DUMMY_POSITION);
+            white_pawn_moves(position.white_pawns(), &position);
         assert_eq!(white_pawn_moves, 0x80011110000 | 0x20000);
     }
 
     #[test]
     fn black_pawns_can_push() {
-        let black_pawn_pushes = black_pawn_pushes(
-            DUMMY_POSITION.black_pawns(),
-            DUMMY_POSITION.empty_squares(),
-        );
+        let position = dummy_position();
+        let black_pawn_pushes =
+            black_pawn_pushes(position.black_pawns(), position.empty_squares());
         assert_eq!(black_pawn_pushes, 0xc0400008200);
     }
 
     #[test]
     fn black_pawns_can_capture() {
+        let position = dummy_position();
         let black_pawn_captures = black_pawn_captures(
-            DUMMY_POSITION.black_pawns(),
-            DUMMY_POSITION.white_pieces(),
+            position.black_pawns(),
+            position.white_pieces(),
         );
         assert_eq!(black_pawn_captures, 0x100);
     }
 
     #[test]
     fn black_pawns_can_move() {
+        let position = dummy_position();
         let black_pawn_moves =
-            black_pawn_moves(DUMMY_POSITION.black_pawns(), &DUMMY_POSITION);
+            black_pawn_moves(position.black_pawns(), &position);
         assert_eq!(black_pawn_moves, 0xc0400008200 | 0x100);
     }
 
@@ -155,4 +239,98 @@ mod tests {
         assert_eq!(white_pawn_moves, 0xc0000000000);
         assert_eq!(black_pawn_moves, 0xc000000);
     }
+
+    #[test]
+    fn pawn_moves_are_listed() {
+        use Square::*;
+
+        let position =
+            Position::from_fen("7k/8/8/3Pp3/8/1p6/P1P5/7K w - e6 0 1").unwrap();
+        let mut moves = MoveList::new();
+        add_pawn_moves(&position, &mut moves, Promotions::All);
+
+        let expected = [
+            Move::new(A2, A3, MoveFlag::Quiet),
+            Move::new(A2, A4, MoveFlag::DoublePush),
+            Move::new(A2, B3, MoveFlag::Capture),
+            Move::new(C2, C3, MoveFlag::Quiet),
+            Move::new(C2, C4, MoveFlag::DoublePush),
+            Move::new(C2, B3, MoveFlag::Capture),
+            Move::new(D5, D6, MoveFlag::Quiet),
+            Move::new(D5, E6, MoveFlag::EnPassant),
+        ];
+        assert_eq!(moves.len(), expected.len());
+        for m in expected {
+            assert!(moves.contains(&m), "{m:?}");
+        }
+    }
+
+    #[test]
+    fn black_pawn_moves_are_listed() {
+        use Square::*;
+
+        let position =
+            Position::from_fen("k7/7p/8/8/3pP3/8/8/K7 b - e3 0 1").unwrap();
+        let mut moves = MoveList::new();
+        add_pawn_moves(&position, &mut moves, Promotions::All);
+
+        let expected = [
+            Move::new(H7, H6, MoveFlag::Quiet),
+            Move::new(H7, H5, MoveFlag::DoublePush),
+            Move::new(D4, D3, MoveFlag::Quiet),
+            Move::new(D4, E3, MoveFlag::EnPassant),
+        ];
+        assert_eq!(moves.len(), expected.len());
+        for m in expected {
+            assert!(moves.contains(&m), "{m:?}");
+        }
+    }
+
+    #[test]
+    fn promotions_are_generated() {
+        use PieceType::*;
+        use Square::*;
+
+        let position =
+            Position::from_fen("1r2k3/P7/8/8/8/8/6p1/4K2N b - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        add_pawn_moves(&position, &mut moves, Promotions::All);
+
+        assert_eq!(moves.len(), 8);
+        for piece in [BlackQueen, BlackRook, BlackBishop, BlackKnight] {
+            assert!(moves.contains(&Move::new_promotion(G2, G1, piece, false)));
+            assert!(moves.contains(&Move::new_promotion(G2, H1, piece, true)));
+        }
+
+        let mut position = position;
+        position.set_side_to_move(PieceColor::White);
+        let mut moves = MoveList::new();
+        add_pawn_moves(&position, &mut moves, Promotions::All);
+
+        assert_eq!(moves.len(), 8);
+        assert!(moves.contains(&Move::new_promotion(A7, A8, WhiteQueen, false)));
+        assert!(moves.contains(&Move::new_promotion(A7, B8, WhiteRook, true)));
+        assert!(moves.iter().all(|m| m.is_promotion()));
+    }
+
+    #[test]
+    fn underpromotions_can_be_limited() {
+        use PieceType::*;
+        use Square::*;
+
+        let position =
+            Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        add_pawn_moves(&position, &mut moves, Promotions::QueenAndKnight);
+
+        assert_eq!(moves.len(), 4);
+        assert!(moves.contains(&Move::new_promotion(
+            A7,
+            A8,
+            WhiteKnight,
+            false
+        )));
+        assert!(moves.contains(&Move::new_promotion(A7, B8, WhiteQueen, true)));
+        assert!(!moves.contains(&Move::new_promotion(A7, A8, WhiteRook, false)));
+    }
 }
@@ -0,0 +1,197 @@
+//! Performance test ("perft") move path enumeration, for checking move
+//! generation against known node counts.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::generate_legal_moves;
+use crate::board::position::Position;
+use crate::moves::Move;
+
+/// Counts the leaf nodes of the legal move tree of the given depth, playing
+/// and taking back every move down to the leaves.
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for m in generate_legal_moves(position) {
+        let undo = position.make_move(m);
+        nodes += perft(position, depth - 1);
+        position.unmake_move(m, undo);
+    }
+    nodes
+}
+
+/// Like `perft`, but bulk counts the last ply: the number of legal moves is
+/// the number of leaves beneath a node, so they're never played. Much faster,
+/// but it doesn't exercise `make_move` at the leaves.
+pub fn perft_bulk(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = generate_legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for m in moves {
+        let undo = position.make_move(m);
+        nodes += perft_bulk(position, depth - 1);
+        position.unmake_move(m, undo);
+    }
+    nodes
+}
+
+/// Like `perft_bulk`, but looks up and stores subtree counts in the given
+/// table, which may be shared between threads.
+pub fn perft_hashed(
+    position: &mut Position,
+    depth: u32,
+    table: &PerftTable,
+) -> u64 {
+    if depth <= 1 {
+        return perft_bulk(position, depth);
+    }
+
+    let key = position.hash();
+    if let Some(nodes) = table.probe(key, depth) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+    for m in generate_legal_moves(position) {
+        let undo = position.make_move(m);
+        nodes += perft_hashed(position, depth - 1, table);
+        position.unmake_move(m, undo);
+    }
+
+    table.store(key, depth, nodes);
+    nodes
+}
+
+/// Like `perft`, but breaks the count down by root move, which helps narrow
+/// down where two move generators disagree.
+pub fn divide(position: &mut Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    generate_legal_moves(position)
+        .into_iter()
+        .map(|m| {
+            let undo = position.make_move(m);
+            let nodes = perft(position, depth - 1);
+            position.unmake_move(m, undo);
+            (m, nodes)
+        })
+        .collect()
+}
+
+/// A fixed-size, always-replace table of perft subtree counts, keyed by
+/// Zobrist hash and depth.
+///
+/// Each entry is a pair of atomics holding the key XORed with the data, and
+/// the data itself (the depth in the top 8 bits and the node count below). A
+/// read racing with a write then just fails to match its key, so the table
+/// can be shared between threads without locking.
+pub struct PerftTable {
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl PerftTable {
+    const DEPTH_SHIFT: u32 = 56;
+
+    /// Creates a table taking up about the given number of megabytes, rounded
+    /// down to a power of two entries (and at least one).
+    pub fn new(megabytes: usize) -> Self {
+        let size = std::mem::size_of::<[AtomicU64; 2]>();
+        let len = (megabytes * 1024 * 1024 / size).max(1);
+        let len = 1 << len.ilog2();
+        Self { entries: (0..len).map(|_| Default::default()).collect() }
+    }
+
+    /// The number of entries in the table.
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    fn entry(&self, key: u64) -> &[AtomicU64; 2] {
+        &self.entries[key as usize & (self.entries.len() - 1)]
+    }
+
+    /// Returns the stored node count for the given key and depth, if any.
+    pub fn probe(&self, key: u64, depth: u32) -> Option<u64> {
+        let [check, data] = self.entry(key);
+        let data = data.load(Ordering::Relaxed);
+        let matches = check.load(Ordering::Relaxed) ^ data == key
+            && data >> Self::DEPTH_SHIFT == depth as u64;
+        matches.then_some(data & ((1 << Self::DEPTH_SHIFT) - 1))
+    }
+
+    /// Stores a node count, replacing whatever was in its entry.
+    pub fn store(&self, key: u64, depth: u32, nodes: u64) {
+        let data = (depth as u64) << Self::DEPTH_SHIFT | nodes;
+        let [check, entry] = self.entry(key);
+        check.store(key ^ data, Ordering::Relaxed);
+        entry.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_counts() {
+        let mut position = Position::starting_position();
+        assert_eq!(perft(&mut position, 0), 1);
+        assert_eq!(perft(&mut position, 1), 20);
+        assert_eq!(perft(&mut position, 2), 400);
+        assert_eq!(perft(&mut position, 3), 8902);
+        assert_eq!(position, Position::starting_position());
+    }
+
+    #[test]
+    fn bulk_counts_match() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w \
+                   KQkq - 0 1";
+        let mut position = Position::from_fen(fen).unwrap();
+        for depth in 0..=2 {
+            assert_eq!(
+                perft_bulk(&mut position, depth),
+                perft(&mut position, depth)
+            );
+        }
+        assert_eq!(perft_bulk(&mut position, 3), 97862);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let mut position = Position::starting_position();
+        let divided = divide(&mut position, 3);
+        assert_eq!(divided.len(), 20);
+        assert_eq!(divided.iter().map(|&(_, n)| n).sum::<u64>(), 8902);
+    }
+
+    #[test]
+    fn hashed_counts_match() {
+        let table = PerftTable::new(1);
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w \
+                   KQkq - 0 1";
+        let mut position = Position::from_fen(fen).unwrap();
+        assert_eq!(perft_hashed(&mut position, 3, &table), 97862);
+        // The second run is answered from the table.
+        assert_eq!(perft_hashed(&mut position, 3, &table), 97862);
+    }
+
+    #[test]
+    fn table_checks_key_and_depth() {
+        let table = PerftTable::new(1);
+        table.store(0x1234, 3, 97862);
+        assert_eq!(table.probe(0x1234, 3), Some(97862));
+        assert_eq!(table.probe(0x1234, 2), None);
+        assert_eq!(table.probe(0x1234 + table.len() as u64, 3), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Lines and segments between pairs of squares, used to find pins and to
+//! block checks.
+
+use std::sync::OnceLock;
+
+use super::sliders::{bishop_attacks, rook_attacks};
+use crate::board::bitboard::*;
+use crate::types::*;
+
+struct RayTables {
+    between: Vec<[BitBoard; 64]>,
+    line:    Vec<[BitBoard; 64]>,
+}
+
+fn tables() -> &'static RayTables {
+    static TABLES: OnceLock<RayTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut between = vec![[EMPTY; 64]; 64];
+        let mut line = vec![[EMPTY; 64]; 64];
+
+        for a in Square::ALL {
+            for b in Square::ALL {
+                if a == b {
+                    continue;
+                }
+                let (i, j) = (a.index() as usize, b.index() as usize);
+                let ends = a.bitboard() | b.bitboard();
+                for attacks in [bishop_attacks, rook_attacks] {
+                    if attacks(a, EMPTY) & b.bitboard() != 0 {
+                        between[i][j] =
+                            attacks(a, b.bitboard()) & attacks(b, a.bitboard());
+                        line[i][j] =
+                            attacks(a, EMPTY) & attacks(b, EMPTY) | ends;
+                    }
+                }
+            }
+        }
+
+        RayTables { between, line }
+    })
+}
+
+/// The squares strictly between two squares on the same rank, file or
+/// diagonal. Empty if the squares aren't aligned.
+pub fn between(a: Square, b: Square) -> BitBoard {
+    tables().between[a.index() as usize][b.index() as usize]
+}
+
+/// The whole rank, file or diagonal passing through both squares, edge to
+/// edge. Empty if the squares aren't aligned.
+pub fn line(a: Square, b: Square) -> BitBoard {
+    tables().line[a.index() as usize][b.index() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn between_is_exclusive() {
+        use Square::*;
+
+        assert_eq!(between(A1, A4), A2.bitboard() | A3.bitboard());
+        assert_eq!(between(H8, E5), G7.bitboard() | F6.bitboard());
+        assert_eq!(between(E4, E5), EMPTY);
+        assert_eq!(between(A1, B3), EMPTY);
+    }
+
+    #[test]
+    fn lines_reach_the_edges() {
+        use Square::*;
+
+        assert_eq!(line(C3, E5), line(A1, H8));
+        assert_eq!(line(B4, G4), RANK_4);
+        assert_eq!(line(D2, D7), FILE_D);
+        assert_eq!(line(A1, B3), EMPTY);
+    }
+}
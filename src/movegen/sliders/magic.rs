@@ -0,0 +1,210 @@
+//! Magic bitboard lookups for sliding attacks.
+//!
+//! For each square, the occupancy of the squares that can block a slider is
+//! multiplied by a "magic" number, and the top bits of the product index a
+//! table of precomputed attacks. The magic numbers below were found by
+//! `find_bishop_magics` and `find_rook_magics` with `MAGIC_SEED`, and can be
+//! regenerated with `cargo run --release --example find_magics`.
+
+use std::sync::OnceLock;
+
+use super::{
+    ray_attacks, relevant_occupancy, subsets, BISHOP_DIRECTIONS,
+    ROOK_DIRECTIONS,
+};
+use crate::board::bitboard::*;
+use crate::types::*;
+use crate::xorshift::Xorshift;
+
+/// The seed that the checked-in magic numbers were generated from.
+pub const MAGIC_SEED: u64 = 0x5eed_cafe_f00d_0001;
+
+#[rustfmt::skip]
+pub const BISHOP_MAGICS: [u64; 64] = [
+    0x0020200102102041, 0x0029021822002000, 0x0011412401010000, 0x02c4440082008010,
+    0x0008484100000010, 0x0002021004180030, 0x420a209008093000, 0x004504010c210441,
+    0x80201010014804a2, 0x000a4204040c2044, 0x4000100c40802802, 0x0060081600c20080,
+    0x0002c42420000210, 0x4080020202620000, 0x0000140082101200, 0x2040210048020844,
+    0x10102c44200a4404, 0x2030000810091041, 0x0004092048002240, 0x0020420401002040,
+    0x1201016820080200, 0x8104800b00514000, 0x8025100080982010, 0x0205042184011100,
+    0x8502220008081008, 0x0602080002500400, 0x8105010810040824, 0x8084010140200880,
+    0x000104008e002100, 0x841400201100a000, 0x08c0a400b2020201, 0x6003010001a18820,
+    0xd0a4b01800404640, 0x0008040401020880, 0x0000104800040800, 0x842b400a00002200,
+    0x0074010200940084, 0x10050501053a0040, 0x022800a404008200, 0x0964012040002400,
+    0x800409084000ea00, 0x22004c0ca0000400, 0x00d40c0048000400, 0x0904848420200400,
+    0x8108100202030490, 0x5001011001000080, 0x10200a8082081311, 0x0214111411000120,
+    0x0800809008202601, 0x000251040120811c, 0x9000004064100400, 0x0492010084110000,
+    0x0005830420820300, 0x0003200202820804, 0x0620821001010328, 0x2420440c00803010,
+    0x0410410490012080, 0x0008420088a41001, 0x4240018024020802, 0x0070090100420200,
+    0x60080410d0221200, 0x5008123002101c40, 0x1000042024840480, 0x220820880a032820,
+];
+
+#[rustfmt::skip]
+pub const ROOK_MAGICS: [u64; 64] = [
+    0x0080046340001080, 0x2040100020004008, 0x8100200008110040, 0x0c80040800821000,
+    0x0a00080200100520, 0x0180020004000180, 0x4a80008002002100, 0x0200040200408021,
+    0x4898800040002080, 0x0801004000802100, 0x0081004100102004, 0x1040808010000800,
+    0x0042000410200a00, 0x1880800200800400, 0x0001000200040100, 0x0806000c08a10042,
+    0x4040a88000400988, 0x0040050041082080, 0x0020010021041042, 0xa000818008011002,
+    0x0800808004000802, 0x0000808004000201, 0x4000040088010210, 0x8008020000804401,
+    0x142a228880044001, 0x0420500040002004, 0x8231001100402000, 0x1200100080800800,
+    0x0008080080040280, 0x200b820080800400, 0x020300130006001c, 0x320410820001184c,
+    0x0908408005002100, 0x0040200040401008, 0x0900801202002042, 0x00a0810801801000,
+    0x4818000880800400, 0x4400800200800400, 0x8040020804001001, 0x1911800058800100,
+    0x00c0008020408000, 0x0110002000524000, 0x1000100020008080, 0x2200100100090020,
+    0x8021024800050010, 0x0001000400490002, 0x448a080210040001, 0x0000008041020024,
+    0x1000400480002480, 0x0021802002401280, 0x0020820010402200, 0x0400100008008080,
+    0x440b000451880100, 0x0006104004200801, 0x0000021008010400, 0x0000004409088200,
+    0x080c410010208001, 0x0020110604802042, 0x0c02110008200045, 0x0000100005a10009,
+    0x4442000810052002, 0x0c0a000108100402, 0x8444020091300804, 0x009204240d024482,
+];
+
+/// Everything needed to look up the attacks from a single square.
+#[derive(Clone, Copy, Debug, Default)]
+struct Magic {
+    mask:   BitBoard,
+    magic:  u64,
+    shift:  u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupancy: BitBoard) -> usize {
+        let product = (occupancy & self.mask).wrapping_mul(self.magic);
+        self.offset + (product >> self.shift) as usize
+    }
+}
+
+/// The magics for each square, along with the shared attack table.
+struct MagicTable {
+    magics:  [Magic; 64],
+    attacks: Vec<BitBoard>,
+}
+
+impl MagicTable {
+    fn new(directions: &[(i8, i8)], magic_numbers: &[u64; 64]) -> Self {
+        let mut magics = [Magic::default(); 64];
+        let mut attacks = Vec::new();
+
+        for square in Square::ALL {
+            let mask = relevant_occupancy(square, directions);
+            let bits = mask.count_ones();
+            let magic = Magic {
+                mask,
+                magic: magic_numbers[square.index() as usize],
+                shift: 64 - bits,
+                offset: attacks.len(),
+            };
+
+            attacks.resize(attacks.len() + (1 << bits), 0);
+            for occupancy in subsets(mask) {
+                attacks[magic.index(occupancy)] =
+                    ray_attacks(square, occupancy, directions);
+            }
+
+            magics[square.index() as usize] = magic;
+        }
+
+        Self { magics, attacks }
+    }
+
+    fn get(&self, square: Square, occupancy: BitBoard) -> BitBoard {
+        self.attacks[self.magics[square.index() as usize].index(occupancy)]
+    }
+}
+
+fn bishop_table() -> &'static MagicTable {
+    static TABLE: OnceLock<MagicTable> = OnceLock::new();
+    TABLE.get_or_init(|| MagicTable::new(&BISHOP_DIRECTIONS, &BISHOP_MAGICS))
+}
+
+fn rook_table() -> &'static MagicTable {
+    static TABLE: OnceLock<MagicTable> = OnceLock::new();
+    TABLE.get_or_init(|| MagicTable::new(&ROOK_DIRECTIONS, &ROOK_MAGICS))
+}
+
+pub fn bishop_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    bishop_table().get(square, occupancy)
+}
+
+pub fn rook_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    rook_table().get(square, occupancy)
+}
+
+/// Numbers with few set bits make better magic candidates.
+fn sparse_random(rng: &mut Xorshift) -> u64 {
+    rng.next_u64() & rng.next_u64() & rng.next_u64()
+}
+
+/// Searches for a magic number for each square, for bishops.
+pub fn find_bishop_magics(seed: u64) -> [u64; 64] {
+    find_magics(&BISHOP_DIRECTIONS, seed)
+}
+
+/// Searches for a magic number for each square, for rooks.
+pub fn find_rook_magics(seed: u64) -> [u64; 64] {
+    find_magics(&ROOK_DIRECTIONS, seed)
+}
+
+fn find_magics(directions: &[(i8, i8)], seed: u64) -> [u64; 64] {
+    let mut rng = Xorshift::new(seed);
+    let mut magics = [0; 64];
+
+    for square in Square::ALL {
+        let mask = relevant_occupancy(square, directions);
+        let bits = mask.count_ones();
+        let cases: Vec<_> = subsets(mask)
+            .map(|occupancy| {
+                (occupancy, ray_attacks(square, occupancy, directions))
+            })
+            .collect();
+
+        let mut table = vec![None; 1 << bits];
+        magics[square.index() as usize] = loop {
+            let candidate = sparse_random(&mut rng);
+            // Quickly reject numbers that can't spread the mask into the
+            // index bits.
+            if (mask.wrapping_mul(candidate) >> 56).count_ones() < 6 {
+                continue;
+            }
+            let magic =
+                Magic { mask, magic: candidate, shift: 64 - bits, offset: 0 };
+            if is_valid(&magic, &cases, &mut table) {
+                break candidate;
+            }
+        };
+    }
+
+    magics
+}
+
+/// Checks that no two occupancies with different attacks share an index.
+fn is_valid(
+    magic: &Magic,
+    cases: &[(BitBoard, BitBoard)],
+    table: &mut [Option<BitBoard>],
+) -> bool {
+    table.fill(None);
+    cases.iter().all(|&(occupancy, attacks)| {
+        let entry = &mut table[magic.index(occupancy)];
+        match *entry {
+            None => {
+                *entry = Some(attacks);
+                true
+            },
+            Some(existing) => existing == attacks,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "slow without optimizations; run with --release --ignored"]
+    fn checked_in_magics_are_reproducible() {
+        assert_eq!(find_bishop_magics(MAGIC_SEED), BISHOP_MAGICS);
+        assert_eq!(find_rook_magics(MAGIC_SEED), ROOK_MAGICS);
+    }
+}
@@ -0,0 +1,188 @@
+//! Attacks for the sliding pieces (bishops, rooks and queens), which depend on
+//! which squares along their rays are occupied.
+//!
+//! Lookups use magic bitboards by default. With the `pext` feature enabled on
+//! x86_64, PEXT-indexed tables are used instead whenever the CPU supports
+//! BMI2.
+
+pub mod magic;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+pub mod pext;
+
+use crate::board::bitboard::*;
+use crate::types::*;
+
+/// Finds every square attacked by a bishop on the given square, given the
+/// occupancy of the board. Occupied squares are included, so the result must
+/// be masked by the enemy pieces to find captures.
+pub fn bishop_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if pext::is_available() {
+        // SAFETY: BMI2 support was just checked.
+        return unsafe { pext::bishop_attacks(square, occupancy) };
+    }
+    magic::bishop_attacks(square, occupancy)
+}
+
+/// Finds every square attacked by a rook on the given square, given the
+/// occupancy of the board. Occupied squares are included, so the result must
+/// be masked by the enemy pieces to find captures.
+pub fn rook_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if pext::is_available() {
+        // SAFETY: BMI2 support was just checked.
+        return unsafe { pext::rook_attacks(square, occupancy) };
+    }
+    magic::rook_attacks(square, occupancy)
+}
+
+/// Finds every square attacked by a queen on the given square, given the
+/// occupancy of the board.
+pub fn queen_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    bishop_attacks(square, occupancy) | rook_attacks(square, occupancy)
+}
+
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Walks outwards from the given square in each (file, rank) direction,
+/// stopping at (and including) the first occupied square. Slow, but obviously
+/// correct, so it's used to build the lookup tables and to test them.
+fn ray_attacks(
+    square: Square,
+    occupancy: BitBoard,
+    directions: &[(i8, i8)],
+) -> BitBoard {
+    let mut attacks = 0;
+
+    for &(file_step, rank_step) in directions {
+        let mut file = square.file() as i8 + file_step;
+        let mut rank = square.rank() as i8 + rank_step;
+
+        while (0..8).contains(&file) && (0..8).contains(&rank) {
+            let target = Square::from_file_rank(file as u8, rank as u8);
+            attacks |= target.bitboard();
+            if occupancy & target.bitboard() != 0 {
+                break;
+            }
+            file += file_step;
+            rank += rank_step;
+        }
+    }
+
+    attacks
+}
+
+/// Finds the squares whose occupancy can affect a slider's attacks from the
+/// given square. The last square of each ray never can, as nothing lies
+/// beyond it.
+fn relevant_occupancy(square: Square, directions: &[(i8, i8)]) -> BitBoard {
+    let mut mask = 0;
+
+    for &(file_step, rank_step) in directions {
+        let mut file = square.file() as i8 + file_step;
+        let mut rank = square.rank() as i8 + rank_step;
+
+        while (0..8).contains(&(file + file_step))
+            && (0..8).contains(&(rank + rank_step))
+        {
+            mask |= Square::from_file_rank(file as u8, rank as u8).bitboard();
+            file += file_step;
+            rank += rank_step;
+        }
+    }
+
+    mask
+}
+
+pub(crate) fn slow_bishop_attacks(
+    square: Square,
+    occupancy: BitBoard,
+) -> BitBoard {
+    ray_attacks(square, occupancy, &BISHOP_DIRECTIONS)
+}
+
+pub(crate) fn slow_rook_attacks(
+    square: Square,
+    occupancy: BitBoard,
+) -> BitBoard {
+    ray_attacks(square, occupancy, &ROOK_DIRECTIONS)
+}
+
+/// Iterates over every subset of the given mask (the "Carry-Rippler" trick),
+/// starting with the empty set.
+fn subsets(mask: BitBoard) -> impl Iterator<Item = BitBoard> {
+    let mut subset: BitBoard = 0;
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let current = subset;
+        subset = subset.wrapping_sub(mask) & mask;
+        done = subset == 0;
+        Some(current)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xorshift::Xorshift;
+
+    #[test]
+    fn ray_attacks_stop_at_blockers() {
+        use Square::*;
+
+        let occupancy = D6.bitboard() | F4.bitboard();
+        assert_eq!(
+            slow_rook_attacks(D4, occupancy),
+            (FILE_D & (RANK_1 | RANK_2 | RANK_3 | RANK_5 | RANK_6))
+                | (RANK_4 & (FILE_A | FILE_B | FILE_C | FILE_E | FILE_F))
+        );
+    }
+
+    #[test]
+    fn relevant_occupancy_excludes_edges() {
+        use Square::*;
+
+        assert_eq!(relevant_occupancy(A1, &ROOK_DIRECTIONS).count_ones(), 12);
+        assert_eq!(relevant_occupancy(D4, &ROOK_DIRECTIONS).count_ones(), 10);
+        assert_eq!(relevant_occupancy(D4, &BISHOP_DIRECTIONS).count_ones(), 9);
+        assert_eq!(relevant_occupancy(A1, &BISHOP_DIRECTIONS).count_ones(), 6);
+        assert_eq!(relevant_occupancy(E4, &BISHOP_DIRECTIONS) & EDGE, EMPTY);
+    }
+
+    #[test]
+    fn subsets_are_enumerated() {
+        let mask = 0b1011;
+        let subsets: Vec<_> = subsets(mask).collect();
+        assert_eq!(subsets, [
+            0b0000, 0b0001, 0b0010, 0b0011, 0b1000, 0b1001, 0b1010, 0b1011
+        ]);
+    }
+
+    #[test]
+    fn lookups_match_ray_walking() {
+        for square in Square::ALL {
+            for occupancy in Xorshift::new(square.index() as u64 + 1)
+                .take(200)
+                .flat_map(|random| [random, random & random >> 7])
+            {
+                assert_eq!(
+                    bishop_attacks(square, occupancy),
+                    slow_bishop_attacks(square, occupancy),
+                );
+                assert_eq!(
+                    rook_attacks(square, occupancy),
+                    slow_rook_attacks(square, occupancy),
+                );
+                assert_eq!(
+                    queen_attacks(square, occupancy),
+                    slow_bishop_attacks(square, occupancy)
+                        | slow_rook_attacks(square, occupancy),
+                );
+            }
+        }
+    }
+}
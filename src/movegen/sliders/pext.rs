@@ -0,0 +1,119 @@
+//! PEXT-indexed lookups for sliding attacks, for x86_64 CPUs with BMI2.
+//!
+//! `_pext_u64` gathers the relevant occupancy bits into a dense index
+//! directly, so no magic numbers are needed. Only compiled with the `pext`
+//! feature, and only used if the CPU turns out to support BMI2 at runtime.
+
+use std::arch::x86_64::_pext_u64;
+use std::sync::OnceLock;
+
+use super::{
+    ray_attacks, relevant_occupancy, subsets, BISHOP_DIRECTIONS,
+    ROOK_DIRECTIONS,
+};
+use crate::board::bitboard::*;
+use crate::types::*;
+
+/// Returns true if the running CPU supports BMI2. The check is only done
+/// once.
+pub fn is_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| std::is_x86_feature_detected!("bmi2"))
+}
+
+/// The relevant occupancy mask for each square, along with the shared attack
+/// table.
+struct PextTable {
+    masks:   [BitBoard; 64],
+    offsets: [usize; 64],
+    attacks: Vec<BitBoard>,
+}
+
+impl PextTable {
+    fn new(directions: &[(i8, i8)]) -> Self {
+        let mut masks = [0; 64];
+        let mut offsets = [0; 64];
+        let mut attacks = Vec::new();
+
+        for square in Square::ALL {
+            let mask = relevant_occupancy(square, directions);
+            masks[square.index() as usize] = mask;
+            offsets[square.index() as usize] = attacks.len();
+
+            // Subsets are enumerated in the same order as the indices PEXT
+            // compresses them to, so no PEXT is needed to build the table.
+            attacks.extend(
+                subsets(mask).map(|occupancy| {
+                    ray_attacks(square, occupancy, directions)
+                }),
+            );
+        }
+
+        Self { masks, offsets, attacks }
+    }
+
+    /// # Safety
+    ///
+    /// The CPU must support BMI2 (see `is_available`).
+    #[target_feature(enable = "bmi2")]
+    unsafe fn get(&self, square: Square, occupancy: BitBoard) -> BitBoard {
+        let i = square.index() as usize;
+        let index = _pext_u64(occupancy, self.masks[i]) as usize;
+        self.attacks[self.offsets[i] + index]
+    }
+}
+
+fn bishop_table() -> &'static PextTable {
+    static TABLE: OnceLock<PextTable> = OnceLock::new();
+    TABLE.get_or_init(|| PextTable::new(&BISHOP_DIRECTIONS))
+}
+
+fn rook_table() -> &'static PextTable {
+    static TABLE: OnceLock<PextTable> = OnceLock::new();
+    TABLE.get_or_init(|| PextTable::new(&ROOK_DIRECTIONS))
+}
+
+/// # Safety
+///
+/// The CPU must support BMI2 (see `is_available`).
+pub unsafe fn bishop_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    bishop_table().get(square, occupancy)
+}
+
+/// # Safety
+///
+/// The CPU must support BMI2 (see `is_available`).
+pub unsafe fn rook_attacks(square: Square, occupancy: BitBoard) -> BitBoard {
+    rook_table().get(square, occupancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::sliders::magic;
+    use crate::xorshift::Xorshift;
+
+    #[test]
+    fn pext_agrees_with_magic() {
+        // There's nothing to compare on CPUs without BMI2.
+        if !is_available() {
+            return;
+        }
+
+        for state in Xorshift::new(0x0123_4567_89ab_cdef).take(20_000) {
+            let square = Square::from_index((state >> 58) as u8);
+            let occupancy = state & state.rotate_left(23);
+
+            unsafe {
+                assert_eq!(
+                    bishop_attacks(square, occupancy),
+                    magic::bishop_attacks(square, occupancy)
+                );
+                assert_eq!(
+                    rook_attacks(square, occupancy),
+                    magic::rook_attacks(square, occupancy)
+                );
+            }
+        }
+    }
+}
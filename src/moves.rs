@@ -0,0 +1,299 @@
+use std::fmt;
+
+use crate::piece::PieceType;
+use crate::types::*;
+
+/// What kind of move a Move is, stored in its top four bits.
+///
+/// The bits are laid out so that the kinds can be tested cheaply: bit 2 is set
+/// for every capture and bit 3 for every promotion, in which case the lowest
+/// two bits give the promoted piece (knight, bishop, rook, queen).
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveFlag {
+    Quiet                = 0b0000,
+    DoublePush           = 0b0001,
+    KingCastle           = 0b0010,
+    QueenCastle          = 0b0011,
+    Capture              = 0b0100,
+    EnPassant            = 0b0101,
+    KnightPromotion      = 0b1000,
+    BishopPromotion      = 0b1001,
+    RookPromotion        = 0b1010,
+    QueenPromotion       = 0b1011,
+    KnightPromotionCapture = 0b1100,
+    BishopPromotionCapture = 0b1101,
+    RookPromotionCapture = 0b1110,
+    QueenPromotionCapture = 0b1111,
+}
+
+impl MoveFlag {
+    const CAPTURE_BIT: u8 = 0b0100;
+    const PROMOTION_BIT: u8 = 0b1000;
+
+    const fn from_bits(bits: u8) -> Option<Self> {
+        use MoveFlag::*;
+        match bits {
+            0b0000 => Some(Quiet),
+            0b0001 => Some(DoublePush),
+            0b0010 => Some(KingCastle),
+            0b0011 => Some(QueenCastle),
+            0b0100 => Some(Capture),
+            0b0101 => Some(EnPassant),
+            0b1000 => Some(KnightPromotion),
+            0b1001 => Some(BishopPromotion),
+            0b1010 => Some(RookPromotion),
+            0b1011 => Some(QueenPromotion),
+            0b1100 => Some(KnightPromotionCapture),
+            0b1101 => Some(BishopPromotionCapture),
+            0b1110 => Some(RookPromotionCapture),
+            0b1111 => Some(QueenPromotionCapture),
+            _ => None,
+        }
+    }
+}
+
+/// A move, packed into 16 bits: six for the origin square, six for the
+/// destination square and four for the MoveFlag.
+///
+/// Castling is encoded as the king capturing its own rook (e.g. e1h1), as the
+/// king's destination alone could be confused with an ordinary king move in
+/// Chess960: with the king on b1, b1c1 may be either. Where the king and rook
+/// end up is given by `castling_destinations`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(u16);
+
+impl Move {
+    /// A placeholder move (a1a1). Never generated for a real position.
+    pub const NULL: Self = Self(0);
+
+    pub const fn new(from: Square, to: Square, flag: MoveFlag) -> Self {
+        Self(
+            from.index() as u16
+                | (to.index() as u16) << 6
+                | (flag as u16) << 12,
+        )
+    }
+
+    /// Constructs a promotion to the given piece, whose color is ignored.
+    /// Panics if the piece is a pawn or king.
+    pub const fn new_promotion(
+        from: Square,
+        to: Square,
+        piece: PieceType,
+        capture: bool,
+    ) -> Self {
+        let kind = match piece.index() % 6 {
+            1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 3,
+            _ => panic!("can only promote to a knight, bishop, rook or queen"),
+        };
+        let capture = if capture { MoveFlag::CAPTURE_BIT } else { 0 };
+        let flag =
+            match MoveFlag::from_bits(MoveFlag::PROMOTION_BIT | capture | kind)
+            {
+                Some(flag) => flag,
+                None => unreachable!(),
+            };
+        Self::new(from, to, flag)
+    }
+
+    /// Reconstructs a Move from the value returned by `Move::bits`. Returns
+    /// None if the flag bits are unused.
+    pub const fn from_bits(bits: u16) -> Option<Self> {
+        match MoveFlag::from_bits((bits >> 12) as u8) {
+            Some(_) => Some(Self(bits)),
+            None => None,
+        }
+    }
+
+    pub const fn bits(self) -> u16 { self.0 }
+
+    pub const fn from(self) -> Square {
+        Square::from_index((self.0 & 0x3f) as u8)
+    }
+    pub const fn to(self) -> Square {
+        Square::from_index((self.0 >> 6 & 0x3f) as u8)
+    }
+
+    pub const fn flag(self) -> MoveFlag {
+        match MoveFlag::from_bits((self.0 >> 12) as u8) {
+            Some(flag) => flag,
+            None => unreachable!(),
+        }
+    }
+
+    const fn flag_bits(self) -> u8 { (self.0 >> 12) as u8 }
+
+    pub const fn is_capture(self) -> bool {
+        self.flag_bits() & MoveFlag::CAPTURE_BIT != 0
+    }
+    pub const fn is_promotion(self) -> bool {
+        self.flag_bits() & MoveFlag::PROMOTION_BIT != 0
+    }
+    pub const fn is_double_push(self) -> bool {
+        matches!(self.flag(), MoveFlag::DoublePush)
+    }
+    pub const fn is_en_passant(self) -> bool {
+        matches!(self.flag(), MoveFlag::EnPassant)
+    }
+    pub const fn is_castle(self) -> bool {
+        matches!(self.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+    }
+
+    /// Returns the squares that the king and rook end up on, if this is a
+    /// castling move: the g- and f-files for kingside castling and the c- and
+    /// d-files for queenside, wherever they started.
+    pub const fn castling_destinations(self) -> Option<(Square, Square)> {
+        let (king_file, rook_file) = match self.flag() {
+            MoveFlag::KingCastle => (6, 5),
+            MoveFlag::QueenCastle => (2, 3),
+            _ => return None,
+        };
+        let rank = self.from().rank();
+        Some((
+            Square::from_file_rank(king_file, rank),
+            Square::from_file_rank(rook_file, rank),
+        ))
+    }
+
+    /// Returns the piece that a pawn is promoted to, if this is a promotion.
+    /// The color is inferred from the destination rank.
+    pub const fn promotion(self) -> Option<PieceType> {
+        use PieceType::*;
+        if !self.is_promotion() {
+            return None;
+        }
+        let white = self.to().rank() == 7;
+        Some(match (self.flag_bits() & 0b11, white) {
+            (0, true) => WhiteKnight,
+            (1, true) => WhiteBishop,
+            (2, true) => WhiteRook,
+            (3, true) => WhiteQueen,
+            (0, false) => BlackKnight,
+            (1, false) => BlackBishop,
+            (2, false) => BlackRook,
+            (_, false) => BlackQueen,
+            (_, true) => unreachable!(),
+        })
+    }
+}
+
+impl fmt::Display for Move {
+    /// Formats the move in UCI long algebraic notation, e.g. "e7e8q".
+    ///
+    /// Castling follows standard chess's convention where it can: with the
+    /// king on the e-file and the rook in a corner, it's written as the
+    /// king's move (e1g1). That includes the Chess960 setups that look like
+    /// that, which Chess960 GUIs expect as e1h1. Any other castling move
+    /// keeps the king-takes-rook form, which can't be mistaken for a king
+    /// move. For Chess960 output, write `from` and `to` instead, which are
+    /// always king-takes-rook.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to = match self.castling_destinations() {
+            Some((king_to, _))
+                if self.from().file() == 4
+                    && matches!(self.to().file(), 0 | 7) =>
+            {
+                king_to
+            },
+            _ => self.to(),
+        };
+        write!(f, "{}{}", self.from(), to)?;
+        if let Some(piece) = self.promotion() {
+            write!(f, "{}", piece.to_char().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Move({self}, {:?})", self.flag())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_round_trip() {
+        use Square::*;
+
+        let m = Move::new(E2, E4, MoveFlag::DoublePush);
+        assert_eq!(m.from(), E2);
+        assert_eq!(m.to(), E4);
+        assert_eq!(m.flag(), MoveFlag::DoublePush);
+        assert!(m.is_double_push());
+        assert!(!m.is_capture());
+        assert_eq!(Move::from_bits(m.bits()), Some(m));
+
+        let m = Move::new(D5, E6, MoveFlag::EnPassant);
+        assert!(m.is_capture() && m.is_en_passant());
+        assert_eq!(m.promotion(), None);
+
+        let m = Move::new(E1, A1, MoveFlag::QueenCastle);
+        assert!(m.is_castle() && !m.is_capture());
+        assert_eq!(m.castling_destinations(), Some((C1, D1)));
+        assert_eq!(
+            Move::new(E1, E2, MoveFlag::Quiet).castling_destinations(),
+            None
+        );
+
+        // In Chess960 the king may already stand on its destination.
+        let m = Move::new(G8, H8, MoveFlag::KingCastle);
+        assert_eq!(m.castling_destinations(), Some((G8, F8)));
+    }
+
+    #[test]
+    fn promotions_are_encoded() {
+        use PieceType::*;
+        use Square::*;
+
+        let m = Move::new_promotion(E7, D8, BlackRook, true);
+        assert_eq!(m.flag(), MoveFlag::RookPromotionCapture);
+        assert_eq!(m.promotion(), Some(WhiteRook));
+        assert!(m.is_capture() && m.is_promotion());
+
+        let m = Move::new_promotion(B2, B1, WhiteKnight, false);
+        assert_eq!(m.flag(), MoveFlag::KnightPromotion);
+        assert_eq!(m.promotion(), Some(BlackKnight));
+        assert!(!m.is_capture());
+    }
+
+    #[test]
+    fn display_is_uci() {
+        use PieceType::*;
+        use Square::*;
+
+        assert_eq!(Move::new(G1, F3, MoveFlag::Quiet).to_string(), "g1f3");
+        assert_eq!(Move::new(E1, H1, MoveFlag::KingCastle).to_string(), "e1g1");
+        assert_eq!(
+            Move::new(E8, A8, MoveFlag::QueenCastle).to_string(),
+            "e8c8"
+        );
+        // Chess960 castling: b1c1 could be a king move.
+        assert_eq!(
+            Move::new(B1, A1, MoveFlag::QueenCastle).to_string(),
+            "b1a1"
+        );
+        assert_eq!(Move::new(G1, H1, MoveFlag::KingCastle).to_string(), "g1h1");
+        assert_eq!(
+            Move::new_promotion(E7, E8, WhiteQueen, false).to_string(),
+            "e7e8q"
+        );
+        assert_eq!(
+            Move::new_promotion(A2, B1, BlackKnight, true).to_string(),
+            "a2b1n"
+        );
+    }
+
+    #[test]
+    fn unused_flags_are_rejected() {
+        assert_eq!(Move::from_bits(0b0110 << 12), None);
+        assert_eq!(Move::from_bits(0b0111 << 12), None);
+    }
+}
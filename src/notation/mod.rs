@@ -0,0 +1,4 @@
+//! Human-readable move notations.
+
+pub mod san;
+pub mod uci;
@@ -0,0 +1,372 @@
+//! Standard Algebraic Notation (SAN), as used in PGN: "e4", "Nbd7", "exd5",
+//! "e8=Q+", "O-O-O#".
+
+use std::fmt;
+
+use crate::board::position::Position;
+use crate::movegen::generate_legal_moves;
+use crate::movegen::legal::CheckInfo;
+use crate::moves::{Move, MoveFlag};
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// The error returned when a string can't be resolved to a legal move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The string isn't SAN, even sloppily.
+    Invalid,
+    /// The string is SAN, but no legal move matches it.
+    Illegal,
+    /// More than one legal move matches the string.
+    Ambiguous,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SanError::Invalid => "invalid SAN",
+            SanError::Illegal => "no legal move matches the SAN",
+            SanError::Ambiguous => "more than one legal move matches the SAN",
+        })
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Move {
+    /// Formats the move, which must be legal in the given position, in SAN.
+    pub fn to_san(self, position: &Position) -> String {
+        let mut san = String::new();
+        let piece = position.piece_at(self.from()).expect("no piece to move");
+
+        match self.flag() {
+            MoveFlag::KingCastle => san.push_str("O-O"),
+            MoveFlag::QueenCastle => san.push_str("O-O-O"),
+            _ if piece.is_pawn() => {
+                if self.is_capture() {
+                    san.push(file_char(self.from()));
+                    san.push('x');
+                }
+                san.push_str(&self.to().to_string());
+                if let Some(promotion) = self.promotion() {
+                    san.push('=');
+                    san.push(kind(promotion).to_char());
+                }
+            },
+            _ => {
+                san.push(kind(piece).to_char());
+                san.push_str(&disambiguation(position, self, piece));
+                if self.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&self.to().to_string());
+            },
+        }
+
+        let after = position.with_move(self);
+        if CheckInfo::new(&after).in_check() {
+            match generate_legal_moves(&after).is_empty() {
+                true => san.push('#'),
+                false => san.push('+'),
+            }
+        }
+
+        san
+    }
+}
+
+/// The part of a piece move's SAN that tells it apart from moves of other
+/// pieces of the same type to the same square: the origin file if that's
+/// enough, otherwise the rank, otherwise both.
+fn disambiguation(position: &Position, m: Move, piece: PieceType) -> String {
+    let others: Vec<_> = generate_legal_moves(position)
+        .into_iter()
+        .filter(|other| {
+            other.to() == m.to()
+                && other.from() != m.from()
+                && !other.is_castle()
+                && position.piece_at(other.from()) == Some(piece)
+        })
+        .collect();
+
+    let from = m.from();
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|other| other.from().file() != from.file()) {
+        file_char(from).to_string()
+    } else if others.iter().all(|other| other.from().rank() != from.rank()) {
+        rank_char(from).to_string()
+    } else {
+        from.to_string()
+    }
+}
+
+impl Position {
+    /// Finds the legal move written in SAN.
+    ///
+    /// Some common sloppiness is accepted: a missing or extra "x", a missing
+    /// "=" before a promotion ("e8Q"), zeroes for castling ("0-0"), a fully
+    /// qualified origin ("Ng1f3"), castling written as a king move ("Kg1")
+    /// and any trailing check, mate or annotation symbols.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let san = san
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end()
+            .trim_end_matches(['+', '#', '!', '?']);
+
+        let castle = match san {
+            "O-O" | "0-0" => Some(MoveFlag::KingCastle),
+            "O-O-O" | "0-0-0" => Some(MoveFlag::QueenCastle),
+            _ => None,
+        };
+        if let Some(flag) = castle {
+            return self.unique_move(|m| m.flag() == flag);
+        }
+
+        let mut chars: Vec<char> =
+            san.chars().filter(|&c| !matches!(c, 'x' | ':' | '-')).collect();
+
+        // The moving piece, if it isn't a pawn.
+        let piece = match chars.first() {
+            Some(&c @ ('N' | 'B' | 'R' | 'Q' | 'K')) => {
+                chars.remove(0);
+                PieceType::from_char(c)
+            },
+            _ => None,
+        };
+
+        // A promotion, with or without the "=".
+        let promotion = match chars.last() {
+            Some(&c) if piece.is_none() && "NBRQnbrq".contains(c) => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                PieceType::from_char(c.to_ascii_uppercase())
+            },
+            _ => None,
+        };
+
+        // The destination, and whatever part of the origin is given.
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(SanError::Invalid);
+        }
+        let to = parse_square(chars[chars.len() - 2], chars[chars.len() - 1])
+            .ok_or(SanError::Invalid)?;
+        let (mut from_file, mut from_rank) = (None, None);
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(c as u8 - b'a')
+                },
+                '1'..='8' if from_rank.is_none() => {
+                    from_rank = Some(c as u8 - b'1')
+                },
+                _ => return Err(SanError::Invalid),
+            }
+        }
+
+        let piece = piece.unwrap_or(PieceType::WhitePawn);
+        let matches = |m: Move, to: Square| {
+            let Some(moving) = self.piece_at(m.from()) else { return false };
+            kind(moving) == piece
+                && m.to() == to
+                && promotion.is_none_or(|p| m.promotion().map(kind) == Some(p))
+                && from_file.is_none_or(|file| m.from().file() == file)
+                && from_rank.is_none_or(|rank| m.from().rank() == rank)
+        };
+
+        // Castling written as a king move only counts if there's no real
+        // king move to that square, which in Chess960 there may be.
+        self.unique_move(|m| !m.is_castle() && matches(m, to)).or_else(
+            |error| match error {
+                SanError::Illegal => self.unique_move(|m| {
+                    m.castling_destinations()
+                        .is_some_and(|(king_to, _)| king_to == to)
+                        && matches(m, m.to())
+                }),
+                error => Err(error),
+            },
+        )
+    }
+
+    /// Returns the only legal move matching the predicate.
+    fn unique_move(
+        &self,
+        predicate: impl Fn(Move) -> bool,
+    ) -> Result<Move, SanError> {
+        let mut matches =
+            generate_legal_moves(self).into_iter().filter(|&m| predicate(m));
+        match (matches.next(), matches.next()) {
+            (Some(m), None) => Ok(m),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous),
+            (None, _) => Err(SanError::Illegal),
+        }
+    }
+}
+
+/// The white piece of the same type, so that pieces can be compared
+/// regardless of color.
+fn kind(piece: PieceType) -> PieceType { piece.with_color(PieceColor::White) }
+
+fn file_char(square: Square) -> char { (b'a' + square.file()) as char }
+fn rank_char(square: Square) -> char { (b'1' + square.rank()) as char }
+
+fn parse_square(file: char, rank: char) -> Option<Square> {
+    match (file, rank) {
+        ('a'..='h', '1'..='8') => {
+            Some(Square::from_file_rank(file as u8 - b'a', rank as u8 - b'1'))
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(fen: &str) -> Position { Position::from_fen(fen).unwrap() }
+
+    fn san(fen: &str, m: Move) -> String { m.to_san(&position(fen)) }
+
+    #[test]
+    fn moves_are_formatted() {
+        use PieceType::*;
+        use Square::*;
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, Move::new(E2, E4, MoveFlag::DoublePush)), "e4");
+        assert_eq!(san(start, Move::new(G1, F3, MoveFlag::Quiet)), "Nf3");
+
+        let fen = "r3k2r/1P6/8/3p4/4P3/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, Move::new(E4, D5, MoveFlag::Capture)), "exd5");
+        assert_eq!(san(fen, Move::new(E1, H1, MoveFlag::KingCastle)), "O-O");
+        assert_eq!(san(fen, Move::new(E1, A1, MoveFlag::QueenCastle)), "O-O-O");
+        assert_eq!(
+            san(fen, Move::new_promotion(B7, A8, WhiteQueen, true)),
+            "bxa8=Q+"
+        );
+        assert_eq!(
+            san(fen, Move::new_promotion(B7, B8, WhiteKnight, false)),
+            "b8=N"
+        );
+    }
+
+    #[test]
+    fn checks_and_mates_are_marked() {
+        use Square::*;
+
+        let fen = "6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, Move::new(A1, A8, MoveFlag::Quiet)), "Ra8#");
+        let fen = "6k1/5pp1/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, Move::new(A1, A8, MoveFlag::Quiet)), "Ra8+");
+    }
+
+    #[test]
+    fn origins_are_disambiguated() {
+        use Square::*;
+
+        // Knights on b1 and f1 can both reach d2: the file is enough.
+        let fen = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!(san(fen, Move::new(B1, D2, MoveFlag::Quiet)), "Nbd2");
+
+        // Rooks on a1 and a5 share a file: the rank is needed.
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, Move::new(A1, A3, MoveFlag::Quiet)), "R1a3");
+
+        // Queens on a1, a3 and c1 all reach b2: a1 needs both.
+        let fen = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+        assert_eq!(san(fen, Move::new(A1, B2, MoveFlag::Quiet)), "Qa1b2");
+        assert_eq!(san(fen, Move::new(C1, B2, MoveFlag::Quiet)), "Qcb2");
+
+        // A pinned knight doesn't count.
+        let fen = "4k3/8/8/8/8/8/4r3/1N2KN2 w - - 0 1";
+        assert_eq!(san(fen, Move::new(B1, D2, MoveFlag::Quiet)), "Nd2");
+    }
+
+    #[test]
+    fn moves_are_parsed() {
+        use PieceType::*;
+        use Square::*;
+
+        let start = Position::starting_position();
+        assert_eq!(
+            start.parse_san("e4"),
+            Ok(Move::new(E2, E4, MoveFlag::DoublePush))
+        );
+        assert_eq!(
+            start.parse_san("Nf3"),
+            Ok(Move::new(G1, F3, MoveFlag::Quiet))
+        );
+        assert_eq!(start.parse_san("e5"), Err(SanError::Illegal));
+        assert_eq!(start.parse_san("Nd2"), Err(SanError::Illegal));
+        assert_eq!(start.parse_san("hello"), Err(SanError::Invalid));
+        assert_eq!(start.parse_san(""), Err(SanError::Invalid));
+
+        let fen = "r3k2r/1P6/8/3p4/4P3/8/8/R3K2R w KQkq - 0 1";
+        let position = position(fen);
+        let queen = Move::new_promotion(B7, A8, WhiteQueen, true);
+        assert_eq!(position.parse_san("bxa8=Q+"), Ok(queen));
+        assert_eq!(position.parse_san("b8"), Err(SanError::Ambiguous));
+        assert_eq!(
+            position.parse_san("O-O-O"),
+            Ok(Move::new(E1, A1, MoveFlag::QueenCastle))
+        );
+    }
+
+    #[test]
+    fn sloppy_moves_are_parsed() {
+        use PieceType::*;
+        use Square::*;
+
+        let fen = "r3k2r/1P6/8/3p4/4P3/5N2/8/RN2K2R w KQkq - 0 1";
+        let position = position(fen);
+
+        let cases = [
+            ("b8Q", Move::new_promotion(B7, B8, WhiteQueen, false)),
+            ("b8q", Move::new_promotion(B7, B8, WhiteQueen, false)),
+            ("ba8=N", Move::new_promotion(B7, A8, WhiteKnight, true)),
+            ("0-0", Move::new(E1, H1, MoveFlag::KingCastle)),
+            ("Kg1", Move::new(E1, H1, MoveFlag::KingCastle)),
+            ("ed5", Move::new(E4, D5, MoveFlag::Capture)),
+            ("e4xd5", Move::new(E4, D5, MoveFlag::Capture)),
+            ("Nbd2", Move::new(B1, D2, MoveFlag::Quiet)),
+            ("Nb1d2", Move::new(B1, D2, MoveFlag::Quiet)),
+            ("Nbxd2", Move::new(B1, D2, MoveFlag::Quiet)),
+            ("Nf3-g5!?", Move::new(F3, G5, MoveFlag::Quiet)),
+        ];
+        for (san, m) in cases {
+            assert_eq!(position.parse_san(san), Ok(m), "{san}");
+        }
+        // Both knights can reach d2.
+        assert_eq!(position.parse_san("Nd2"), Err(SanError::Ambiguous));
+
+        let en_passant = self::position("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        for san in ["exd6", "exd6e.p.", "exd6 e.p.", "ed6 e.p."] {
+            assert_eq!(
+                en_passant.parse_san(san),
+                Ok(Move::new(E5, D6, MoveFlag::EnPassant)),
+                "{san}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - \
+             0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ];
+        for fen in fens {
+            let position = position(fen);
+            for m in generate_legal_moves(&position) {
+                let san = m.to_san(&position);
+                assert_eq!(position.parse_san(&san), Ok(m), "{san}");
+            }
+        }
+    }
+}
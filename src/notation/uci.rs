@@ -0,0 +1,197 @@
+//! UCI long algebraic notation: "e2e4", "e7e8q", and castling as either
+//! "e1g1" (the king's destination) or "e1h1" (the king taking its own rook,
+//! as Chess960 GUIs send it).
+//!
+//! Formatting is `Move`'s Display implementation; this module resolves
+//! strings against a position, which is needed to tell what kind of move
+//! they are.
+
+use std::fmt;
+
+use crate::board::position::Position;
+use crate::movegen::generate_legal_moves;
+use crate::moves::Move;
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// The error returned when a string can't be resolved to a legal move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UciMoveError {
+    /// The string isn't a move in UCI notation.
+    Invalid,
+    /// No legal move matches the string.
+    Illegal,
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UciMoveError::Invalid => "invalid UCI move",
+            UciMoveError::Illegal => "no legal move matches the UCI move",
+        })
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+impl Position {
+    /// Finds the legal move written in UCI notation, with all of its flags.
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, UciMoveError> {
+        let uci = uci.trim();
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(UciMoveError::Invalid);
+        }
+
+        let from: Square =
+            uci[0..2].parse().map_err(|_| UciMoveError::Invalid)?;
+        let to: Square =
+            uci[2..4].parse().map_err(|_| UciMoveError::Invalid)?;
+        let promotion = match uci[4..].chars().next() {
+            None => None,
+            Some(c) => match PieceType::from_char(c.to_ascii_uppercase()) {
+                Some(
+                    piece @ (PieceType::WhiteKnight
+                    | PieceType::WhiteBishop
+                    | PieceType::WhiteRook
+                    | PieceType::WhiteQueen),
+                ) => Some(piece),
+                _ => return Err(UciMoveError::Invalid),
+            },
+        };
+
+        let moves = generate_legal_moves(self);
+        let candidates = || {
+            moves.iter().copied().filter(|m| {
+                let promoted = m
+                    .promotion()
+                    .map(|piece| piece.with_color(PieceColor::White));
+                m.from() == from && promoted == promotion
+            })
+        };
+
+        // An exact match is either an ordinary move or the king taking its
+        // own rook, which can only be castling. Failing that, the king's
+        // destination may stand for castling, as in "e1g1"; in Chess960 that
+        // square can also be reached by an ordinary king move, which wins.
+        candidates()
+            .find(|m| m.to() == to)
+            .or_else(|| {
+                candidates().find(|m| {
+                    m.castling_destinations()
+                        .is_some_and(|(king_to, _)| king_to == to)
+                })
+            })
+            .ok_or(UciMoveError::Illegal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::castling::CastlingRights;
+    use crate::moves::MoveFlag;
+
+    fn position(fen: &str) -> Position { Position::from_fen(fen).unwrap() }
+
+    #[test]
+    fn moves_are_flagged() {
+        use PieceType::*;
+        use Square::*;
+
+        let start = Position::starting_position();
+        assert_eq!(
+            start.parse_uci_move("e2e4"),
+            Ok(Move::new(E2, E4, MoveFlag::DoublePush))
+        );
+        assert_eq!(
+            start.parse_uci_move("g1f3"),
+            Ok(Move::new(G1, F3, MoveFlag::Quiet))
+        );
+
+        let position = position("1r2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(
+            position.parse_uci_move("e5d6"),
+            Ok(Move::new(E5, D6, MoveFlag::EnPassant))
+        );
+        assert_eq!(
+            position.parse_uci_move("a7b8q"),
+            Ok(Move::new_promotion(A7, B8, WhiteQueen, true))
+        );
+        assert_eq!(
+            position.parse_uci_move("a7a8n"),
+            Ok(Move::new_promotion(A7, A8, WhiteKnight, false))
+        );
+    }
+
+    #[test]
+    fn both_castling_encodings_are_accepted() {
+        use Square::*;
+
+        let standard = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let short = Move::new(E1, H1, MoveFlag::KingCastle);
+        let long = Move::new(E1, A1, MoveFlag::QueenCastle);
+        assert_eq!(standard.parse_uci_move("e1g1"), Ok(short));
+        assert_eq!(standard.parse_uci_move("e1h1"), Ok(short));
+        assert_eq!(standard.parse_uci_move("e1c1"), Ok(long));
+        assert_eq!(standard.parse_uci_move("e1a1"), Ok(long));
+
+        // Without the right, e1h1 is just an (impossible) king move.
+        let no_kingside = position("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1");
+        assert_eq!(
+            no_kingside.parse_uci_move("e1h1"),
+            Err(UciMoveError::Illegal)
+        );
+
+        // A Chess960 setup with the king on b1 and rooks on a1 and g1.
+        let mut chess960 = position("1k6/8/8/8/8/8/8/RK4R1 w KQ - 0 1");
+        chess960.set_castling_rook(CastlingRights::WHITE_KINGSIDE, G1);
+        chess960.set_castling_rook(CastlingRights::WHITE_QUEENSIDE, A1);
+        assert_eq!(
+            chess960.parse_uci_move("b1g1"),
+            Ok(Move::new(B1, G1, MoveFlag::KingCastle))
+        );
+        assert_eq!(
+            chess960.parse_uci_move("b1a1"),
+            Ok(Move::new(B1, A1, MoveFlag::QueenCastle))
+        );
+    }
+
+    #[test]
+    fn king_moves_win_over_castling_to_the_same_square() {
+        use Square::*;
+
+        // Both b1c1 and castling queenside (b1a1) are legal.
+        let mut chess960 = position("1k6/8/8/8/8/8/8/RK4R1 w KQ - 0 1");
+        chess960.set_castling_rook(CastlingRights::WHITE_KINGSIDE, G1);
+        chess960.set_castling_rook(CastlingRights::WHITE_QUEENSIDE, A1);
+        let castle = Move::new(B1, A1, MoveFlag::QueenCastle);
+        assert!(generate_legal_moves(&chess960).contains(&castle));
+        assert_eq!(
+            chess960.parse_uci_move("b1c1"),
+            Ok(Move::new(B1, C1, MoveFlag::Quiet))
+        );
+        assert_eq!(chess960.parse_uci_move("b1a1"), Ok(castle));
+
+        // g1 is out of the king's reach, so b1g1 can only mean castling.
+        let mut far = position("1k6/8/8/8/8/8/8/RK5R w KQ - 0 1");
+        far.set_castling_rook(CastlingRights::WHITE_KINGSIDE, H1);
+        far.set_castling_rook(CastlingRights::WHITE_QUEENSIDE, A1);
+        let short = Move::new(B1, H1, MoveFlag::KingCastle);
+        assert_eq!(far.parse_uci_move("b1g1"), Ok(short));
+        assert_eq!(far.parse_uci_move("b1h1"), Ok(short));
+        assert_eq!(
+            far.parse_uci_move("b1c1"),
+            Ok(Move::new(B1, C1, MoveFlag::Quiet))
+        );
+    }
+
+    #[test]
+    fn bad_input_is_rejected() {
+        let start = Position::starting_position();
+        for uci in ["", "e2", "e2e9", "e2e4e", "i2e4", "0000", "e7e8k"] {
+            assert_eq!(start.parse_uci_move(uci), Err(UciMoveError::Invalid));
+        }
+        assert_eq!(start.parse_uci_move("e2e5"), Err(UciMoveError::Illegal));
+        assert_eq!(start.parse_uci_move("e2e4q"), Err(UciMoveError::Illegal));
+    }
+}
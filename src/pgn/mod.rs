@@ -0,0 +1,124 @@
+//! Portable Game Notation (PGN): reading and writing games as text.
+//!
+//! A game is read into a PgnGame: its tags, its starting position, and a tree
+//! of moves in which each move carries its comments, NAGs (numeric
+//! annotation glyphs, like `$1` for "good move") and any variations that
+//! could have been played instead of it.
+
+pub mod reader;
+pub mod writer;
+
+pub use reader::{PgnError, PgnErrorKind, Reader};
+pub use writer::Writer;
+
+use crate::board::position::Position;
+use crate::game::GameResult;
+use crate::moves::Move;
+
+/// The tags that every PGN game should have, in the order they're exported.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// A game's tag pairs, in the order they were read or set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tags(Vec<(String, String)>);
+
+impl Tags {
+    pub const fn new() -> Self { Self(Vec::new()) }
+
+    /// Returns the value of the tag with the given name, if it's present.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, replacing its value if it's already present.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.0.iter_mut().find(|(n, _)| n == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.0.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Removes a tag, returning its value if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.0.iter().position(|(n, _)| n == name)?;
+        Some(self.0.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize { self.0.len() }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+/// A game as read from (or to be written to) PGN.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags:     Tags,
+    /// The position before the first move: the standard starting position
+    /// unless the tags give a FEN.
+    pub start:    Position,
+    /// Comments before the first move.
+    pub comments: Vec<String>,
+    /// The main line.
+    pub moves:    Vec<PgnMove>,
+    pub result:   GameResult,
+}
+
+impl PgnGame {
+    /// An empty game from the standard starting position.
+    pub fn new() -> Self {
+        Self {
+            tags:     Tags::new(),
+            start:    Position::starting_position(),
+            comments: Vec::new(),
+            moves:    Vec::new(),
+            result:   GameResult::Ongoing,
+        }
+    }
+
+    /// The moves of the main line.
+    pub fn mainline(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|m| m.mv)
+    }
+}
+
+impl Default for PgnGame {
+    fn default() -> Self { Self::new() }
+}
+
+/// A move in a PGN game, with its annotations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv:         Move,
+    pub nags:       Vec<u8>,
+    /// Comments after the move. Where they stood relative to the variations
+    /// isn't kept: a comment after a variation ends up here too, and is
+    /// written before the variations, so `1. e4 (1. d4) {c}` comes back as
+    /// `1. e4 {c} (1. d4)`.
+    pub comments:   Vec<String>,
+    /// Lines that could have been played instead of this move.
+    pub variations: Vec<Variation>,
+}
+
+impl PgnMove {
+    pub const fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// An alternative line, starting from the position before the move that it
+/// belongs to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variation {
+    /// Comments before the first move.
+    pub comments: Vec<String>,
+    pub moves:    Vec<PgnMove>,
+}
@@ -0,0 +1,733 @@
+//! Streaming PGN reader.
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use super::{PgnGame, PgnMove, Variation};
+use crate::board::fen::FenError;
+use crate::board::position::Position;
+use crate::game::GameResult;
+use crate::notation::san::SanError;
+
+/// The leading comments, moves and result token of a line of movetext.
+type Line = (Vec<String>, Vec<PgnMove>, Option<GameResult>);
+
+/// What went wrong while reading a game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    /// The underlying reader failed.
+    Io(io::ErrorKind),
+    /// A character that isn't valid at this point.
+    UnexpectedChar(char),
+    /// A tag pair wasn't closed before the end of the line.
+    UnterminatedTag,
+    /// A `{` comment wasn't closed before the end of the input.
+    UnterminatedComment,
+    /// A `(` variation wasn't closed before the end of the game.
+    UnterminatedVariation,
+    /// A variation appeared before any move that it could replace.
+    VariationWithoutMove,
+    /// An annotation appeared before any move that it could apply to.
+    NagWithoutMove,
+    /// A `$` wasn't followed by a number from 0 to 255, or a run of `!` and
+    /// `?` wasn't a known annotation.
+    BadNag,
+    /// The FEN tag couldn't be parsed.
+    BadFen(FenError),
+    /// A move couldn't be resolved in the position it was played in.
+    BadMove { san: String, error: SanError },
+}
+
+/// The error returned when a game can't be read. Records the (one-based)
+/// line and column at which the problem was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    pub line:   usize,
+    pub column: usize,
+    pub kind:   PgnErrorKind,
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnErrorKind::Io(kind) => write!(f, "read failed: {kind}"),
+            PgnErrorKind::UnexpectedChar(c) => {
+                write!(f, "unexpected character {c:?}")
+            },
+            PgnErrorKind::UnterminatedTag => write!(f, "unterminated tag"),
+            PgnErrorKind::UnterminatedComment => {
+                write!(f, "unterminated comment")
+            },
+            PgnErrorKind::UnterminatedVariation => {
+                write!(f, "unterminated variation")
+            },
+            PgnErrorKind::VariationWithoutMove => {
+                write!(f, "variation before the first move")
+            },
+            PgnErrorKind::NagWithoutMove => {
+                write!(f, "annotation before the first move")
+            },
+            PgnErrorKind::BadNag => write!(f, "invalid annotation"),
+            PgnErrorKind::BadFen(error) => write!(f, "{error}"),
+            PgnErrorKind::BadMove { san, error } => {
+                write!(f, "bad move {san:?}: {error}")
+            },
+        }
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PGN error at {}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// Reads games one at a time from PGN text.
+///
+/// After an error, the rest of the broken game is skipped: the next game read
+/// starts at the next tag section (a line starting with `[` that follows one
+/// that doesn't).
+pub struct Reader<R> {
+    chars:  Chars<R>,
+    resync: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { chars: Chars::new(reader), resync: false }
+    }
+
+    /// Reads the next game, returning None at the end of the input.
+    pub fn read_game(&mut self) -> Option<Result<PgnGame, PgnError>> {
+        if std::mem::take(&mut self.resync) {
+            if let Err(error) = self.chars.skip_to_next_game() {
+                return Some(Err(error));
+            }
+        }
+
+        match self.parse_game() {
+            Ok(game) => game.map(Ok),
+            Err(error) => {
+                self.resync = true;
+                Some(Err(error))
+            },
+        }
+    }
+
+    fn parse_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        self.skip_whitespace()?;
+        if self.chars.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut game = PgnGame::new();
+        let mut fen = None;
+        while self.chars.peek()? == Some('[') {
+            let location = self.chars.location();
+            let (name, value) = self.parse_tag()?;
+            if name == "FEN" {
+                fen = Some((value.clone(), location));
+            }
+            game.tags.set(&name, &value);
+            self.skip_whitespace()?;
+        }
+
+        if let Some((fen, (line, column))) = fen {
+            game.start = Position::from_fen(&fen).map_err(|error| {
+                PgnError { line, column, kind: PgnErrorKind::BadFen(error) }
+            })?;
+        }
+
+        let (comments, moves, result) = self.parse_line(&game.start, 0)?;
+        game.comments = comments;
+        game.moves = moves;
+        game.result = result
+            .or_else(|| game.tags.get("Result").and_then(GameResult::from_pgn))
+            .unwrap_or_default();
+
+        Ok(Some(game))
+    }
+
+    /// Parses `[Name "value"]`, with `\"` and `\\` escapes in the value.
+    fn parse_tag(&mut self) -> Result<(String, String), PgnError> {
+        self.chars.bump();
+        self.skip_spaces()?;
+
+        let mut name = String::new();
+        while let Some(c) = self.chars.peek_in_line() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            self.chars.bump();
+        }
+        self.skip_spaces()?;
+        self.expect('"')?;
+
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.chars.peek_in_line() else {
+                return Err(self.error(PgnErrorKind::UnterminatedTag));
+            };
+            self.chars.bump();
+            match c {
+                '"' => break,
+                '\\' => match self.chars.peek_in_line() {
+                    Some(escaped @ ('"' | '\\')) => {
+                        value.push(escaped);
+                        self.chars.bump();
+                    },
+                    _ => value.push('\\'),
+                },
+                '\n' => return Err(self.error(PgnErrorKind::UnterminatedTag)),
+                c => value.push(c),
+            }
+        }
+
+        self.skip_spaces()?;
+        self.expect(']')?;
+        Ok((name, value))
+    }
+
+    /// Parses a line of movetext from the given position, up to the end of
+    /// the game (or, for a variation, its closing parenthesis). Returns the
+    /// comments before the first move, the moves and the result, if one was
+    /// given.
+    fn parse_line(
+        &mut self,
+        start: &Position,
+        depth: usize,
+    ) -> Result<Line, PgnError> {
+        let mut position = start.clone();
+        let mut before_last = None;
+        let mut comments = Vec::new();
+        let mut moves: Vec<PgnMove> = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+            let location = self.chars.location();
+            let Some(c) = self.chars.peek()? else {
+                if depth > 0 {
+                    return Err(self.error(PgnErrorKind::UnterminatedVariation));
+                }
+                return Ok((comments, moves, None));
+            };
+
+            match c {
+                // The start of the next game's tags, when there's no result.
+                '[' if self.chars.at_line_start() => {
+                    if depth > 0 {
+                        let kind = PgnErrorKind::UnterminatedVariation;
+                        return Err(self.error(kind));
+                    }
+                    return Ok((comments, moves, None));
+                },
+                '{' | ';' => {
+                    let comment = match c {
+                        '{' => self.parse_brace_comment()?,
+                        _ => self.parse_line_comment(),
+                    };
+                    match moves.last_mut() {
+                        Some(last) => last.comments.push(comment),
+                        None => comments.push(comment),
+                    }
+                },
+                '$' | '!' | '?' => {
+                    let nag = self.parse_nag()?;
+                    let Some(last) = moves.last_mut() else {
+                        return Err(self
+                            .error_at(location, PgnErrorKind::NagWithoutMove));
+                    };
+                    last.nags.push(nag);
+                },
+                '(' => {
+                    self.chars.bump();
+                    let Some(before) = &before_last else {
+                        return Err(self.error_at(
+                            location,
+                            PgnErrorKind::VariationWithoutMove,
+                        ));
+                    };
+                    let (comments, line, _) =
+                        self.parse_line(before, depth + 1)?;
+                    let last = moves.last_mut().unwrap();
+                    last.variations.push(Variation { comments, moves: line });
+                },
+                ')' if depth > 0 => {
+                    self.chars.bump();
+                    return Ok((comments, moves, None));
+                },
+                '.' => self.chars.bump(),
+                '*' => {
+                    self.chars.bump();
+                    if depth == 0 {
+                        return Ok((
+                            comments,
+                            moves,
+                            Some(GameResult::Ongoing),
+                        ));
+                    }
+                },
+                c if is_symbol_char(c) => {
+                    let token = self.parse_symbol();
+                    if token.chars().all(|c| c.is_ascii_digit()) {
+                        // A move number; its dots are skipped separately.
+                        continue;
+                    }
+                    if let Some(result) = GameResult::from_pgn(&token) {
+                        if depth == 0 {
+                            return Ok((comments, moves, Some(result)));
+                        }
+                        continue;
+                    }
+
+                    let m = position.parse_san(&token).map_err(|error| {
+                        let kind = PgnErrorKind::BadMove { san: token, error };
+                        self.error_at(location, kind)
+                    })?;
+                    before_last = Some(position.clone());
+                    position.make_move(m);
+                    moves.push(PgnMove::new(m));
+                },
+                c => {
+                    return Err(self.error(PgnErrorKind::UnexpectedChar(c)));
+                },
+            }
+        }
+    }
+
+    /// Parses a `{...}` comment, which may span lines, returning its text
+    /// with surrounding whitespace removed.
+    fn parse_brace_comment(&mut self) -> Result<String, PgnError> {
+        let location = self.chars.location();
+        self.chars.bump();
+
+        let mut comment = String::new();
+        loop {
+            match self.chars.peek()? {
+                Some('}') => {
+                    self.chars.bump();
+                    return Ok(comment.trim().to_string());
+                },
+                Some(c) => {
+                    comment.push(c);
+                    self.chars.bump();
+                },
+                None => {
+                    let kind = PgnErrorKind::UnterminatedComment;
+                    return Err(self.error_at(location, kind));
+                },
+            }
+        }
+    }
+
+    /// Parses a `;` comment, which runs to the end of the line.
+    fn parse_line_comment(&mut self) -> String {
+        self.chars.bump();
+        let mut comment = String::new();
+        while let Some(c) = self.chars.peek_in_line() {
+            comment.push(c);
+            self.chars.bump();
+        }
+        comment.trim().to_string()
+    }
+
+    /// Parses `$n`, or one of the traditional suffixes like `!?`.
+    fn parse_nag(&mut self) -> Result<u8, PgnError> {
+        let location = self.chars.location();
+        let mut text = String::new();
+        if self.chars.peek_in_line() == Some('$') {
+            self.chars.bump();
+            while let Some(c @ '0'..='9') = self.chars.peek_in_line() {
+                text.push(c);
+                self.chars.bump();
+            }
+            return text
+                .parse()
+                .map_err(|_| self.error_at(location, PgnErrorKind::BadNag));
+        }
+
+        while let Some(c @ ('!' | '?')) = self.chars.peek_in_line() {
+            text.push(c);
+            self.chars.bump();
+        }
+        match text.as_str() {
+            "!" => Ok(1),
+            "?" => Ok(2),
+            "!!" => Ok(3),
+            "??" => Ok(4),
+            "!?" => Ok(5),
+            "?!" => Ok(6),
+            _ => Err(self.error_at(location, PgnErrorKind::BadNag)),
+        }
+    }
+
+    /// Parses a move, move number or result.
+    fn parse_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        while let Some(c) = self.chars.peek_in_line() {
+            if !is_symbol_char(c) {
+                break;
+            }
+            symbol.push(c);
+            self.chars.bump();
+        }
+        symbol
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), PgnError> {
+        match self.chars.peek_in_line() {
+            Some(c) if c == expected => {
+                self.chars.bump();
+                Ok(())
+            },
+            Some('\n') | None => Err(self.error(PgnErrorKind::UnterminatedTag)),
+            Some(c) => Err(self.error(PgnErrorKind::UnexpectedChar(c))),
+        }
+    }
+
+    /// Skips whitespace, including newlines, and `%` escape lines.
+    fn skip_whitespace(&mut self) -> Result<(), PgnError> {
+        while let Some(c) = self.chars.peek()? {
+            if c == '%' && self.chars.at_line_start() {
+                self.chars.skip_line();
+            } else if c.is_whitespace() {
+                self.chars.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips whitespace without moving on to the next line.
+    fn skip_spaces(&mut self) -> Result<(), PgnError> {
+        while let Some(c) = self.chars.peek_in_line() {
+            if c == '\n' || !c.is_whitespace() {
+                break;
+            }
+            self.chars.bump();
+        }
+        Ok(())
+    }
+
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        self.error_at(self.chars.location(), kind)
+    }
+
+    fn error_at(
+        &self,
+        (line, column): (usize, usize),
+        kind: PgnErrorKind,
+    ) -> PgnError {
+        PgnError { line, column, kind }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<PgnGame, PgnError>;
+    fn next(&mut self) -> Option<Self::Item> { self.read_game() }
+}
+
+/// Characters that can appear in moves, move numbers and results.
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(c, '_' | '+' | '#' | '=' | ':' | '-' | '/')
+}
+
+/// The input, a line at a time, with the position in it.
+struct Chars<R> {
+    reader:      R,
+    line:        Vec<char>,
+    pos:         usize,
+    line_number: usize,
+    done:        bool,
+}
+
+impl<R: BufRead> Chars<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, line: Vec::new(), pos: 0, line_number: 0, done: false }
+    }
+
+    /// Returns the next character, reading another line if needed.
+    fn peek(&mut self) -> Result<Option<char>, PgnError> {
+        while self.pos >= self.line.len() {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.line[self.pos]))
+    }
+
+    /// Returns the next character on the current line (including its
+    /// newline), if any.
+    fn peek_in_line(&self) -> Option<char> { self.line.get(self.pos).copied() }
+
+    fn bump(&mut self) { self.pos += 1; }
+
+    fn skip_line(&mut self) { self.pos = self.line.len(); }
+
+    fn at_line_start(&self) -> bool { self.pos == 0 }
+
+    fn location(&self) -> (usize, usize) { (self.line_number, self.pos + 1) }
+
+    /// Reads the next line, returning false at the end of the input. Bytes
+    /// that aren't UTF-8 are replaced rather than failing the whole file.
+    fn next_line(&mut self) -> Result<bool, PgnError> {
+        if self.done {
+            return Ok(false);
+        }
+
+        let mut bytes = Vec::new();
+        match self.reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => {
+                self.done = true;
+                Ok(false)
+            },
+            Ok(_) => {
+                self.line = String::from_utf8_lossy(&bytes).chars().collect();
+                self.pos = 0;
+                self.line_number += 1;
+                Ok(true)
+            },
+            Err(error) => {
+                self.done = true;
+                Err(PgnError {
+                    line:   self.line_number + 1,
+                    column: 1,
+                    kind:   PgnErrorKind::Io(error.kind()),
+                })
+            },
+        }
+    }
+
+    /// Skips to the start of the next tag section, unless already there.
+    fn skip_to_next_game(&mut self) -> Result<(), PgnError> {
+        let mut in_tags = self.line.first() == Some(&'[');
+        if in_tags && self.at_line_start() {
+            return Ok(());
+        }
+
+        self.skip_line();
+        while self.next_line()? {
+            let is_tag = self.line.first() == Some(&'[');
+            if is_tag && !in_tags {
+                return Ok(());
+            }
+            in_tags = is_tag;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::{Move, MoveFlag};
+    use crate::types::*;
+
+    fn read(pgn: &str) -> Vec<Result<PgnGame, PgnError>> {
+        Reader::new(pgn.as_bytes()).collect()
+    }
+
+    fn read_one(pgn: &str) -> PgnGame {
+        let mut games = read(pgn);
+        assert_eq!(games.len(), 1);
+        games.remove(0).unwrap()
+    }
+
+    const GAMES: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+[Annotator "Someone \"quoted\""]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 1/2-1/2
+
+[Event "Second"]
+[Result "0-1"]
+
+1.f3 e5 2.g4?? Qh4# 0-1
+"#;
+
+    #[test]
+    fn games_and_tags_are_read() {
+        use Square::*;
+
+        let games: Vec<_> =
+            read(GAMES).into_iter().map(Result::unwrap).collect();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tags.len(), 8);
+        assert_eq!(game.tags.get("White"), Some("Fischer, Robert J."));
+        assert_eq!(game.tags.get("Annotator"), Some(r#"Someone "quoted""#));
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(game.moves.len(), 10);
+        assert_eq!(game.moves[4].comments, [
+            "This opening is called the Ruy Lopez."
+        ]);
+        assert_eq!(game.moves[8].mv, Move::new(E1, H1, MoveFlag::KingCastle));
+
+        let game = &games[1];
+        assert_eq!(game.result, GameResult::BlackWins);
+        assert_eq!(game.moves[2].nags, [4]);
+        assert_eq!(
+            game.mainline().last(),
+            Some(Move::new(D8, H4, MoveFlag::Quiet))
+        );
+    }
+
+    #[test]
+    fn annotations_are_attached() {
+        let game = read_one(
+            "{Before anything} 1. e4 $1 {Best by test} ; a line comment\ne5!? \
+             {[%clk 0:01:23]} *",
+        );
+        assert_eq!(game.comments, ["Before anything"]);
+        assert_eq!(game.moves[0].nags, [1]);
+        assert_eq!(game.moves[0].comments, ["Best by test", "a line comment"]);
+        assert_eq!(game.moves[1].nags, [5]);
+        assert_eq!(game.moves[1].comments, ["[%clk 0:01:23]"]);
+        assert_eq!(game.result, GameResult::Ongoing);
+    }
+
+    #[test]
+    fn variations_are_nested() {
+        use Square::*;
+
+        let game = read_one(
+            "1. e4 (1. d4 d5 (1... Nf6 2. c4 (2. Nf3)) 2. c4) (1. c4) 1... e5 \
+             ({Or} 1... c5) 1-0",
+        );
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.result, GameResult::WhiteWins);
+
+        let e4 = &game.moves[0];
+        assert_eq!(e4.variations.len(), 2);
+        let d4 = &e4.variations[0].moves;
+        assert_eq!(d4.len(), 3);
+        assert_eq!(d4[0].mv, Move::new(D2, D4, MoveFlag::DoublePush));
+
+        // 1... Nf6 replaces 1... d5, and 2. Nf3 replaces 2. c4 within it.
+        let nf6 = &d4[1].variations[0].moves;
+        assert_eq!(nf6[0].mv, Move::new(G8, F6, MoveFlag::Quiet));
+        assert_eq!(
+            nf6[1].variations[0].moves[0].mv,
+            Move::new(G1, F3, MoveFlag::Quiet)
+        );
+
+        let e5 = &game.moves[1];
+        assert_eq!(e5.variations[0].comments, ["Or"]);
+        assert_eq!(
+            e5.variations[0].moves[0].mv,
+            Move::new(C7, C5, MoveFlag::DoublePush)
+        );
+    }
+
+    #[test]
+    fn comments_after_variations_join_the_move() {
+        let game = read_one("1. e4 {a} (1. d4) {b} e5 *");
+        assert_eq!(game.moves[0].comments, ["a", "b"]);
+        assert_eq!(game.moves[0].variations.len(), 1);
+    }
+
+    #[test]
+    fn fen_tags_set_the_start() {
+        use Square::*;
+
+        let game = read_one(
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 w - - 0 1\"]\n\n1. \
+             Ra8# 1-0",
+        );
+        assert_eq!(game.start.to_fen(), "4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(game.moves[0].mv, Move::new(A1, A8, MoveFlag::Quiet));
+    }
+
+    #[test]
+    fn games_without_results_end_at_the_next_tags() {
+        let games = read("1. e4 e5\n[Event \"Next\"]\n1. d4\n% escaped\n");
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].as_ref().unwrap().moves.len(), 2);
+        assert_eq!(games[0].as_ref().unwrap().result, GameResult::Ongoing);
+        assert_eq!(games[1].as_ref().unwrap().tags.get("Event"), Some("Next"));
+    }
+
+    #[test]
+    fn errors_have_locations() {
+        let games = read("[Event \"x\"]\n\n1. e4 e5 2. Ke3 Nc6 *\n");
+        assert_eq!(
+            games[0],
+            Err(PgnError {
+                line:   3,
+                column: 13,
+                kind:   PgnErrorKind::BadMove {
+                    san:   "Ke3".to_string(),
+                    error: SanError::Illegal,
+                },
+            })
+        );
+
+        let games = read("1. e4 {unclosed\n");
+        assert_eq!(games[0].as_ref().unwrap_err().line, 1);
+        assert_eq!(
+            games[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::UnterminatedComment
+        );
+
+        let games = read("[Event \"x]\n");
+        assert_eq!(
+            games[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::UnterminatedTag
+        );
+
+        let games = read("(1. e4) *");
+        assert_eq!(
+            games[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::VariationWithoutMove
+        );
+
+        for (pgn, column) in [("$1 1. e4 *", 1), ("1. e4 (!? 1. d4) *", 8)] {
+            let error = read(pgn).remove(0).unwrap_err();
+            assert_eq!((error.line, error.column), (1, column), "{pgn}");
+            assert_eq!(error.kind, PgnErrorKind::NagWithoutMove, "{pgn}");
+        }
+    }
+
+    #[test]
+    fn malformed_games_are_skipped() {
+        let pgn = "[Event \"1\"]\n\n1. e4 e5 *\n\n[Event \"2\"]\n[Date \
+                   \"bad]\n\n1. e4 *\n\n[Event \"3\"]\n\n1. e4 e4 2. d4 \
+                   *\n\n[Event \"4\"]\n\n1. e4 (1. d4\n\n[Event \"5\"]\n\n1. \
+                   d4 *\n";
+        let games = read(pgn);
+        let events: Vec<_> = games
+            .iter()
+            .map(|game| {
+                game.as_ref().map(|game| game.tags.get("Event").unwrap())
+            })
+            .collect();
+
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0], Ok("1"));
+        assert!(events[1].is_err());
+        assert!(events[2].is_err());
+        assert_eq!(
+            games[3].as_ref().unwrap_err().kind,
+            PgnErrorKind::UnterminatedVariation
+        );
+        assert_eq!(events[4], Ok("5"));
+    }
+
+    #[test]
+    fn empty_input_has_no_games() {
+        assert!(read("").is_empty());
+        assert!(read("\n  \n% just an escape\n").is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+//! PGN writer.
+
+use std::io::{self, Write};
+
+use super::{PgnGame, PgnMove, SEVEN_TAG_ROSTER};
+use crate::board::position::Position;
+use crate::piece::PieceColor;
+
+/// Writes games as PGN in the export format: the seven tag roster first,
+/// then any other tags, then movetext wrapped to a maximum line width.
+///
+/// Comments, variations and NAGs are all written by default; each can be
+/// left out. Commands embedded in comments, like `[%clk 0:01:23]`, are kept
+/// even when comments aren't, since they're data rather than commentary.
+///
+/// Comment text isn't always written exactly as given: a `}` can't appear
+/// inside a brace comment, so any are dropped, and runs of whitespace may
+/// become a single space or a line break when the movetext is wrapped.
+pub struct Writer<W> {
+    writer:     W,
+    line_width: usize,
+    comments:   bool,
+    variations: bool,
+    nags:       bool,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a writer that wraps movetext at 80 columns and keeps every
+    /// annotation.
+    pub const fn new(writer: W) -> Self {
+        Self {
+            writer,
+            line_width: 80,
+            comments: true,
+            variations: true,
+            nags: true,
+        }
+    }
+
+    /// Sets the maximum length of a line of movetext. A token longer than
+    /// the width gets a line to itself; a width of 0 disables wrapping.
+    pub const fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width;
+        self
+    }
+
+    /// Sets whether comments are written.
+    pub const fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Sets whether variations are written.
+    pub const fn variations(mut self, variations: bool) -> Self {
+        self.variations = variations;
+        self
+    }
+
+    /// Sets whether NAGs are written.
+    pub const fn nags(mut self, nags: bool) -> Self {
+        self.nags = nags;
+        self
+    }
+
+    /// Writes a game, followed by a blank line.
+    pub fn write_game(&mut self, game: &PgnGame) -> io::Result<()> {
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => game.result.to_string(),
+                _ => game.tags.get(name).unwrap_or(default_tag(name)).into(),
+            };
+            write_tag(&mut self.writer, name, &value)?;
+        }
+
+        let standard_start = game.start == Position::starting_position();
+        if !standard_start {
+            write_tag(&mut self.writer, "SetUp", "1")?;
+            write_tag(&mut self.writer, "FEN", &game.start.to_fen())?;
+        }
+        for (name, value) in game.tags.iter() {
+            let derived = matches!(name, "SetUp" | "FEN");
+            if !SEVEN_TAG_ROSTER.contains(&name) && !derived {
+                write_tag(&mut self.writer, name, value)?;
+            }
+        }
+        writeln!(self.writer)?;
+
+        let mut movetext = Movetext::new(self.line_width);
+        self.write_comments(&mut movetext, &game.comments);
+        self.write_line(&mut movetext, &game.start, &game.moves);
+        movetext.token(&game.result.to_string());
+        writeln!(self.writer, "{}", movetext.text)?;
+        writeln!(self.writer)
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W { self.writer }
+
+    /// Writes a line of moves played from the given position, with their
+    /// annotations and variations.
+    fn write_line(
+        &self,
+        movetext: &mut Movetext,
+        start: &Position,
+        moves: &[PgnMove],
+    ) {
+        let mut position = start.clone();
+        // Black's moves need their own number at the start of a line, and
+        // after anything that interrupts the moves.
+        let mut needs_number = true;
+
+        for m in moves {
+            let number = position.fullmove_number();
+            match position.side_to_move() {
+                PieceColor::White => movetext.token(&format!("{number}.")),
+                PieceColor::Black if needs_number => {
+                    movetext.token(&format!("{number}..."));
+                },
+                PieceColor::Black => {},
+            }
+            movetext.token(&m.mv.to_san(&position));
+            needs_number = false;
+
+            if self.nags {
+                for nag in &m.nags {
+                    movetext.token(&format!("${nag}"));
+                }
+            }
+            needs_number |= self.write_comments(movetext, &m.comments);
+
+            if self.variations {
+                for variation in &m.variations {
+                    movetext.open_variation();
+                    self.write_comments(movetext, &variation.comments);
+                    self.write_line(movetext, &position, &variation.moves);
+                    movetext.close_variation();
+                    needs_number = true;
+                }
+            }
+
+            position.make_move(m.mv);
+        }
+    }
+
+    /// Writes comments, or just the commands in them if comments are off.
+    /// Returns true if anything was written.
+    fn write_comments(
+        &self,
+        movetext: &mut Movetext,
+        comments: &[String],
+    ) -> bool {
+        let mut written = false;
+        for comment in comments {
+            let comment = match self.comments {
+                true => comment.clone(),
+                false => commands(comment).join(" "),
+            };
+            if self.comments || !comment.is_empty() {
+                movetext.comment(&comment);
+                written = true;
+            }
+        }
+        written
+    }
+}
+
+/// The value exported for a missing tag from the seven tag roster.
+fn default_tag(name: &str) -> &'static str {
+    match name {
+        "Date" => "????.??.??",
+        _ => "?",
+    }
+}
+
+fn write_tag(
+    writer: &mut impl Write,
+    name: &str,
+    value: &str,
+) -> io::Result<()> {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(writer, "[{name} \"{value}\"]")
+}
+
+/// Returns the `[%...]` commands embedded in a comment.
+fn commands(comment: &str) -> Vec<&str> {
+    let mut commands = Vec::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        let Some(end) = rest[start..].find(']') else { break };
+        commands.push(&rest[start..start + end + 1]);
+        rest = &rest[start + end + 1..];
+    }
+    commands
+}
+
+/// Splits a comment into the words that it may be wrapped between, keeping
+/// each `[%...]` command whole.
+fn words(comment: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = comment.trim_start();
+    while !rest.is_empty() {
+        let end = match rest.starts_with("[%") {
+            true => rest.find(']').map(|end| end + 1),
+            false => None,
+        };
+        let end = end
+            .or_else(|| rest.find(char::is_whitespace))
+            .unwrap_or(rest.len());
+        words.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    words
+}
+
+/// Movetext being built up a token at a time, wrapped as it goes.
+struct Movetext {
+    text:       String,
+    line_len:   usize,
+    line_width: usize,
+    /// Whether the next token is separated from the last by a space; it
+    /// isn't just after an opening parenthesis.
+    space:      bool,
+}
+
+impl Movetext {
+    const fn new(line_width: usize) -> Self {
+        Self { text: String::new(), line_len: 0, line_width, space: false }
+    }
+
+    fn token(&mut self, token: &str) {
+        let len = token.chars().count();
+        if self.line_len > 0 {
+            let space = usize::from(self.space);
+            if self.line_width > 0
+                && self.line_len + space + len > self.line_width
+            {
+                self.text.push('\n');
+                self.line_len = 0;
+            } else if self.space {
+                self.text.push(' ');
+                self.line_len += 1;
+            }
+        }
+        self.text.push_str(token);
+        self.line_len += len;
+        self.space = true;
+    }
+
+    /// Writes a brace comment, which may be broken across lines at spaces,
+    /// but not inside a `[%...]` command. A `}` would end the comment early,
+    /// so any in the text are dropped.
+    fn comment(&mut self, comment: &str) {
+        let comment = comment.replace('}', "");
+        let words = words(&comment);
+        match words.as_slice() {
+            [] => self.token("{}"),
+            [word] => self.token(&format!("{{{word}}}")),
+            [first, middle @ .., last] => {
+                self.token(&format!("{{{first}"));
+                for word in middle {
+                    self.token(word);
+                }
+                self.token(&format!("{last}}}"));
+            },
+        }
+    }
+
+    fn open_variation(&mut self) {
+        self.token("(");
+        self.space = false;
+    }
+
+    fn close_variation(&mut self) {
+        self.space = false;
+        self.token(")");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::Reader;
+
+    fn read(pgn: &str) -> PgnGame {
+        Reader::new(pgn.as_bytes()).next().unwrap().unwrap()
+    }
+
+    fn write(game: &PgnGame, writer: Writer<Vec<u8>>) -> String {
+        let mut writer = writer;
+        writer.write_game(game).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    fn export(pgn: &str) -> String {
+        write(&read(pgn), Writer::new(Vec::new()))
+    }
+
+    #[test]
+    fn tags_are_written_in_export_order() {
+        let pgn = export(
+            "[Annotator \"Someone \\\"quoted\\\"\"]\n[White \"A\"]\n[Event \
+             \"E\"]\n[Result \"1-0\"]\n\n1. e4 *",
+        );
+        assert_eq!(
+            pgn,
+            "[Event \"E\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \
+             \"?\"]\n[White \"A\"]\n[Black \"?\"]\n[Result \"*\"]\n[Annotator \
+             \"Someone \\\"quoted\\\"\"]\n\n1. e4 *\n\n"
+        );
+    }
+
+    #[test]
+    fn setup_tags_come_from_the_start() {
+        let pgn = export(
+            "[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 5\"]\n[SetUp \"1\"]\n\n5... \
+             Kd7 6. Ra7+ *",
+        );
+        assert!(pgn.contains(
+            "[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - \
+             0 5\"]\n\n5... Kd7 6. Ra7+ *\n"
+        ));
+    }
+
+    #[test]
+    fn black_moves_are_numbered_after_interruptions() {
+        let movetext = |pgn: &str| {
+            let pgn = export(pgn);
+            pgn.split("\n\n").nth(1).unwrap().to_string()
+        };
+
+        assert_eq!(
+            movetext(
+                "{Start} 1. e4 $1 e5 2. Nf3 {Knight} Nc6 (2... d6 3. d4 (3. \
+                 Bc4)) 3. Bb5 *"
+            ),
+            "{Start} 1. e4 $1 e5 2. Nf3 {Knight} 2... Nc6 (2... d6 3. d4 (3. \
+             Bc4)) 3. Bb5 *"
+        );
+        assert_eq!(
+            movetext("1. e4 ({Or} 1. d4) 1... e5 *"),
+            "1. e4 ({Or} 1. d4) 1... e5 *"
+        );
+    }
+
+    #[test]
+    fn movetext_is_wrapped() {
+        let game = read(
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy \
+             Lopez, after a sixteenth century priest.} 3... a6 4. Ba4 Nf6 5. \
+             O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. \
+             c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 1/2-1/2",
+        );
+        let pgn = write(&game, Writer::new(Vec::new()));
+        let movetext: Vec<_> = pgn.lines().skip(8).collect();
+        assert!(movetext.len() > 2);
+        assert!(movetext.iter().all(|line| line.len() <= 80));
+        assert!(movetext.iter().all(|line| !line.starts_with(' ')));
+        // Comments are broken at spaces, so only their newlines change.
+        let reread = read(&pgn);
+        assert!(reread.mainline().eq(game.mainline()));
+        assert_eq!(
+            reread.moves[4].comments[0].replace('\n', " "),
+            game.moves[4].comments[0]
+        );
+
+        let narrow = write(&game, Writer::new(Vec::new()).line_width(20));
+        assert!(narrow.lines().skip(8).all(|line| line.len() <= 20));
+
+        let unwrapped = write(&game, Writer::new(Vec::new()).line_width(0));
+        assert_eq!(unwrapped.lines().count(), 10);
+    }
+
+    #[test]
+    fn annotations_can_be_stripped() {
+        let game = read(
+            "{Intro} 1. e4 $1 {[%clk 0:01:23] Good} e5 {[%clk 0:01:20]} (1... \
+             c5 {Sicilian}) 2. Nf3 *",
+        );
+        let movetext = |writer: Writer<Vec<u8>>| {
+            let pgn = write(&game, writer);
+            pgn.split("\n\n").nth(1).unwrap().to_string()
+        };
+
+        assert_eq!(
+            movetext(Writer::new(Vec::new()).line_width(0)),
+            "{Intro} 1. e4 $1 {[%clk 0:01:23] Good} 1... e5 {[%clk 0:01:20]} \
+             (1... c5 {Sicilian}) 2. Nf3 *"
+        );
+        assert_eq!(
+            movetext(Writer::new(Vec::new()).line_width(0).comments(false)),
+            "1. e4 $1 {[%clk 0:01:23]} 1... e5 {[%clk 0:01:20]} (1... c5) 2. \
+             Nf3 *"
+        );
+        assert_eq!(
+            movetext(
+                Writer::new(Vec::new())
+                    .line_width(0)
+                    .variations(false)
+                    .nags(false)
+            ),
+            "{Intro} 1. e4 {[%clk 0:01:23] Good} 1... e5 {[%clk 0:01:20]} 2. \
+             Nf3 *"
+        );
+    }
+
+    #[test]
+    fn clock_annotations_round_trip() {
+        let game = read(
+            "[Event \"Blitz\"]\n\n1. d4 {[%clk 0:03:00]} 1... d5 {[%clk \
+             0:02:59]} 0-1",
+        );
+        let pgn = write(&game, Writer::new(Vec::new()));
+        let reread = read(&pgn);
+        assert_eq!(reread.moves, game.moves);
+        assert_eq!(reread.moves[1].comments, ["[%clk 0:02:59]"]);
+        assert_eq!(write(&reread, Writer::new(Vec::new())), pgn);
+    }
+
+    #[test]
+    fn wrapped_clock_annotations_round_trip() {
+        let moves = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6"];
+        let movetext: Vec<_> = moves
+            .iter()
+            .enumerate()
+            .map(|(i, san)| format!("{san} {{[%clk 0:0{i}:59] Fine}}"))
+            .collect();
+        let game = read(&format!("{} *", movetext.join(" ")));
+
+        for width in [20, 30, 80] {
+            let pgn = write(&game, Writer::new(Vec::new()).line_width(width));
+            assert!(pgn.lines().count() > 9, "{pgn}");
+            assert!(!pgn.lines().any(|line| line.ends_with("[%clk")), "{pgn}");
+            // Wrapping may turn spaces between words into line breaks, but
+            // the moves and their clock times must come back unchanged.
+            let reread = read(&pgn);
+            assert_eq!(reread.moves.len(), game.moves.len());
+            for (before, after) in game.moves.iter().zip(&reread.moves) {
+                assert_eq!(after.mv, before.mv);
+                assert_eq!(
+                    commands(&after.comments[0]),
+                    commands(&before.comments[0]),
+                    "{pgn}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn closing_braces_are_dropped_from_comments() {
+        let game = read("1. e4 ; a } b\n*");
+        assert_eq!(game.moves[0].comments, ["a } b"]);
+
+        let pgn = write(&game, Writer::new(Vec::new()));
+        assert!(pgn.contains("1. e4 {a b} *"), "{pgn}");
+        assert_eq!(read(&pgn).moves[0].comments, ["a b"]);
+    }
+}
@@ -11,6 +11,7 @@ pub struct Piece {
 /// like, as the former can be represented as a u8, while the latter requires
 /// more space as it technically contains multiple values.
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PieceType {
     WhitePawn,
     WhiteKnight,
@@ -27,12 +28,77 @@ pub enum PieceType {
 }
 
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PieceColor {
     White,
     Black,
 }
 
+impl std::ops::Not for PieceColor {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
 impl PieceType {
+    /// Every PieceType, in declaration (and therefore index) order.
+    #[rustfmt::skip]
+    pub const ALL: [PieceType; 12] = {
+        use PieceType::*;
+        [
+            WhitePawn, WhiteKnight, WhiteBishop, WhiteRook, WhiteQueen, WhiteKing,
+            BlackPawn, BlackKnight, BlackBishop, BlackRook, BlackQueen, BlackKing,
+        ]
+    };
+
+    pub const fn index(self) -> u8 { self as u8 }
+
+    /// Returns the PieceType with the given index. Panics if the index is not
+    /// less than 12.
+    pub const fn from_index(index: u8) -> Self { Self::ALL[index as usize] }
+
+    /// Returns the piece of the same kind, but of the given color.
+    pub const fn with_color(self, color: PieceColor) -> Self {
+        Self::from_index(self.index() % 6 + 6 * color as u8)
+    }
+
+    pub const fn is_pawn(self) -> bool { self.index().is_multiple_of(6) }
+    pub const fn is_knight(self) -> bool { self.index() % 6 == 1 }
+    pub const fn is_bishop(self) -> bool { self.index() % 6 == 2 }
+    pub const fn is_rook(self) -> bool { self.index() % 6 == 3 }
+    pub const fn is_queen(self) -> bool { self.index() % 6 == 4 }
+    pub const fn is_king(self) -> bool { self.index() % 6 == 5 }
+
+    /// Returns the PieceType for a FEN-style piece letter, where uppercase
+    /// letters are white pieces and lowercase letters are black pieces.
+    pub const fn from_char(c: char) -> Option<Self> {
+        use PieceType::*;
+        match c {
+            'P' => Some(WhitePawn),
+            'N' => Some(WhiteKnight),
+            'B' => Some(WhiteBishop),
+            'R' => Some(WhiteRook),
+            'Q' => Some(WhiteQueen),
+            'K' => Some(WhiteKing),
+            'p' => Some(BlackPawn),
+            'n' => Some(BlackKnight),
+            'b' => Some(BlackBishop),
+            'r' => Some(BlackRook),
+            'q' => Some(BlackQueen),
+            'k' => Some(BlackKing),
+            _ => None,
+        }
+    }
+
+    /// Returns the FEN-style letter for this PieceType.
+    pub const fn to_char(self) -> char {
+        "PNBRQKpnbrqk".as_bytes()[self as usize] as char
+    }
+
     pub fn color(&self) -> PieceColor {
         use PieceColor::*;
         use PieceType::*;
@@ -0,0 +1,264 @@
+//! Static Exchange Evaluation (SEE): the material balance of the sequence of
+//! captures on a single square that a move starts, assuming each side always
+//! recaptures with its least valuable piece and stops when recapturing would
+//! lose material.
+
+use crate::board::bitboard::*;
+use crate::board::position::Position;
+use crate::movegen::sliders::{bishop_attacks, rook_attacks};
+use crate::moves::Move;
+use crate::piece::PieceType::*;
+use crate::piece::{PieceColor, PieceType};
+use crate::types::*;
+
+/// The material values used by SEE, in centipawns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PieceValues {
+    pub pawn:   i32,
+    pub knight: i32,
+    pub bishop: i32,
+    pub rook:   i32,
+    pub queen:  i32,
+    /// Only matters when a (pseudo-legal) move lets the king be captured.
+    pub king:   i32,
+}
+
+impl PieceValues {
+    pub const fn value(&self, piece: PieceType) -> i32 {
+        match piece.index() % 6 {
+            0 => self.pawn,
+            1 => self.knight,
+            2 => self.bishop,
+            3 => self.rook,
+            4 => self.queen,
+            _ => self.king,
+        }
+    }
+}
+
+impl Default for PieceValues {
+    fn default() -> Self {
+        Self {
+            pawn:   100,
+            knight: 320,
+            bishop: 330,
+            rook:   500,
+            queen:  900,
+            king:   20_000,
+        }
+    }
+}
+
+/// Returns the material that the side to move gains (or, if negative, loses)
+/// by playing the given move and following up with the best sequence of
+/// captures on its destination square.
+pub fn see(position: &Position, m: Move) -> i32 {
+    see_with(position, m, &PieceValues::default())
+}
+
+/// Like `see`, but with the given piece values.
+pub fn see_with(position: &Position, m: Move, values: &PieceValues) -> i32 {
+    if m.is_castle() {
+        return 0;
+    }
+
+    let (from, to) = (m.from(), m.to());
+    let Some(piece) = position.piece_at(from) else { return 0 };
+    let us = piece.color();
+    let promotion_gain = values.queen - values.pawn;
+
+    let mut occupancy = position.all_pieces() & !from.bitboard();
+    let captured = match m.is_en_passant() {
+        true => {
+            let pawn = Square::from_file_rank(to.file(), from.rank());
+            occupancy &= !pawn.bitboard();
+            values.pawn
+        },
+        false => position.piece_at(to).map_or(0, |p| values.value(p)),
+    };
+
+    // gain[d] is the material won by the side making the d-th capture,
+    // assuming that it's the last one.
+    let mut gain = [0; 32];
+    let mut depth = 0;
+    let mut on_square = match m.promotion() {
+        Some(promotion) => {
+            gain[0] = captured + values.value(promotion) - values.pawn;
+            values.value(promotion)
+        },
+        None => {
+            gain[0] = captured;
+            values.value(piece)
+        },
+    };
+
+    let bishops = position[WhiteBishop] | position[BlackBishop];
+    let rooks = position[WhiteRook] | position[BlackRook];
+    let queens = position[WhiteQueen] | position[BlackQueen];
+    let last_rank = RANK_1 | RANK_8;
+
+    let mut attackers = position.attackers_to(to, occupancy) & occupancy;
+    let mut side = !us;
+
+    while depth + 1 < gain.len() {
+        let ours = attackers & position[side];
+        let Some((square, attacker)) = least_valuable(position, ours, side)
+        else {
+            break;
+        };
+
+        // The king can't capture onto a defended square.
+        if attacker.is_king() && attackers & position[!side] != 0 {
+            break;
+        }
+
+        depth += 1;
+        gain[depth] = on_square - gain[depth - 1];
+        on_square = values.value(attacker);
+        if attacker.is_pawn() && to.bitboard() & last_rank != 0 {
+            gain[depth] += promotion_gain;
+            on_square = values.queen;
+        }
+
+        // Moving the attacker off may reveal a slider behind it.
+        occupancy &= !square.bitboard();
+        attackers &= occupancy;
+        attackers |= bishop_attacks(to, occupancy) & (bishops | queens);
+        attackers |= rook_attacks(to, occupancy) & (rooks | queens);
+        attackers &= occupancy;
+
+        side = !side;
+    }
+
+    // Each side may choose not to recapture.
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+    gain[0]
+}
+
+/// Returns true if `see` is at least the given threshold.
+pub fn see_ge(position: &Position, m: Move, threshold: i32) -> bool {
+    see(position, m) >= threshold
+}
+
+/// Like `see_ge`, but with the given piece values.
+pub fn see_ge_with(
+    position: &Position,
+    m: Move,
+    threshold: i32,
+    values: &PieceValues,
+) -> bool {
+    see_with(position, m, values) >= threshold
+}
+
+/// Finds the least valuable of the given pieces of the given color.
+fn least_valuable(
+    position: &Position,
+    pieces: BitBoard,
+    color: PieceColor,
+) -> Option<(Square, PieceType)> {
+    [WhitePawn, WhiteKnight, WhiteBishop, WhiteRook, WhiteQueen, WhiteKing]
+        .into_iter()
+        .map(|piece| piece.with_color(color))
+        .find_map(|piece| {
+            let candidates = pieces & position[piece];
+            let square = candidates.squares().next()?;
+            Some((square, piece))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveFlag;
+
+    fn position(fen: &str) -> Position { Position::from_fen(fen).unwrap() }
+
+    #[test]
+    fn undefended_pieces_are_won() {
+        use Square::*;
+
+        let position =
+            position("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1");
+        let m = Move::new(E1, E5, MoveFlag::Capture);
+        assert_eq!(see(&position, m), 100);
+        assert!(see_ge(&position, m, 100));
+        assert!(!see_ge(&position, m, 101));
+    }
+
+    #[test]
+    fn defended_pieces_cost_the_capturer() {
+        use Square::*;
+
+        let position = position(
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+        );
+        let m = Move::new(D3, E5, MoveFlag::Capture);
+        assert_eq!(see(&position, m), 100 - 320);
+
+        let values = PieceValues { knight: 325, ..Default::default() };
+        assert_eq!(see_with(&position, m, &values), -225);
+        assert!(see_ge_with(&position, m, -225, &values));
+    }
+
+    #[test]
+    fn x_rays_are_revealed() {
+        use Square::*;
+
+        let m = Move::new(E2, E5, MoveFlag::Capture);
+        let doubled = position("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1");
+        assert_eq!(see(&doubled, m), 100);
+
+        let single = position("4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1");
+        assert_eq!(see(&single, m), 100 - 500);
+    }
+
+    #[test]
+    fn quiet_moves_can_lose_material() {
+        use Square::*;
+
+        let position = position("4k3/8/3p4/8/3Q4/8/8/4K3 w - - 0 1");
+        assert_eq!(see(&position, Move::new(D4, E5, MoveFlag::Quiet)), -900);
+        assert_eq!(see(&position, Move::new(D4, D5, MoveFlag::Quiet)), 0);
+    }
+
+    #[test]
+    fn kings_only_capture_undefended_pieces() {
+        use Square::*;
+
+        let m = Move::new(E1, D2, MoveFlag::Capture);
+
+        // d2 is defended by the pawn on c3, so Kxd2 isn't legal, and SEE
+        // sees the king being lost.
+        let defended = position("4k3/8/8/8/8/2p5/3p4/4K3 w - - 0 1");
+        assert!(see(&defended, m) < 0);
+
+        let undefended = position("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1");
+        assert_eq!(see(&undefended, m), 100);
+    }
+
+    #[test]
+    fn promotions_are_counted() {
+        use Square::*;
+
+        // dxe8=Q wins a rook for a pawn, even though the knight recaptures.
+        let knight = position("4r2k/3P4/5n2/8/8/8/8/7K w - - 0 1");
+        let m = Move::new_promotion(D7, E8, WhiteQueen, true);
+        assert_eq!(see(&knight, m), 400);
+
+        // Black won't take the queen on c8, as dxc8=Q would follow.
+        let rook = position("r1n4k/3P4/8/8/8/8/8/2Q4K w - - 0 1");
+        assert_eq!(see(&rook, Move::new(C1, C8, MoveFlag::Capture)), 320);
+    }
+
+    #[test]
+    fn en_passant_captures_a_pawn() {
+        use Square::*;
+
+        let position = position("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        let m = Move::new(E5, D6, MoveFlag::EnPassant);
+        assert_eq!(see(&position, m), 100);
+    }
+}
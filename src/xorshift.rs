@@ -0,0 +1,25 @@
+//! A small, seedable xorshift generator, for reproducible random numbers
+//! without pulling in a dependency: used to search for magic numbers, and by
+//! tests that want arbitrary but repeatable inputs.
+
+/// Marsaglia's 64-bit xorshift generator, with shifts 13, 7 and 17. The seed
+/// must not be zero, or every number will be zero.
+#[derive(Clone, Debug)]
+pub(crate) struct Xorshift(u64);
+
+impl Xorshift {
+    pub const fn new(seed: u64) -> Self { Self(seed) }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+impl Iterator for Xorshift {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> { Some(self.next_u64()) }
+}
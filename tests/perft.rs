@@ -0,0 +1,63 @@
+//! Runs the standard perft suite (the starting position, "Kiwipete" and
+//! positions 3 to 6 from the Chess Programming Wiki) against the legal move
+//! generator.
+//!
+//! Each line of `perft.epd` is a FEN followed by `;D<depth> <nodes>` entries.
+//! Small counts are checked by default; the rest take a while, so they're
+//! behind `#[ignore]` (run them with `cargo test --release -- --ignored`).
+
+use chess::board::position::Position;
+use chess::movegen::perft::perft;
+
+const SUITE: &str = include_str!("perft.epd");
+
+/// Node counts above this are only checked by the ignored test.
+const QUICK_LIMIT: u64 = 10_000;
+
+struct Entry {
+    fen:    &'static str,
+    depths: Vec<(u32, u64)>,
+}
+
+fn suite() -> Vec<Entry> {
+    SUITE
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split(';');
+            let fen = fields.next().unwrap().trim();
+            let depths = fields
+                .map(|field| {
+                    let (depth, nodes) =
+                        field.trim().split_once(' ').expect("malformed entry");
+                    let depth = depth.strip_prefix('D').expect("missing D");
+                    (depth.parse().unwrap(), nodes.parse().unwrap())
+                })
+                .collect();
+            Entry { fen, depths }
+        })
+        .collect()
+}
+
+fn run(filter: impl Fn(u64) -> bool) {
+    for entry in suite() {
+        let mut position = Position::from_fen(entry.fen).unwrap();
+        for &(depth, expected) in &entry.depths {
+            if filter(expected) {
+                assert_eq!(
+                    perft(&mut position, depth),
+                    expected,
+                    "{} at depth {depth}",
+                    entry.fen
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn perft_suite_quick() { run(|nodes| nodes <= QUICK_LIMIT); }
+
+#[test]
+#[ignore = "slow; run with --release --ignored"]
+fn perft_suite_full() { run(|nodes| nodes > QUICK_LIMIT); }